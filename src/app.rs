@@ -1,5 +1,20 @@
-use accord_network::{Connection, FullNodeCommand, User};
-use tokio::sync::mpsc;
+use accord_network::{Connection, FullNodeCommand, Message, User};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use ratatui::layout::Rect;
+use unicode_width::UnicodeWidthChar;
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{config::Config, counts::MessageCounts, node::NodeEvent, theme::Theme, trust::TrustStore};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum NodeStatus {
@@ -16,78 +31,960 @@ impl std::fmt::Display for NodeStatus {
     }
 }
 
+pub const DEFAULT_PROMPT_PREFIX: &str = ">";
+
+/// Number of function keys (F1 upwards) available to the quick-bar.
+pub const QUICKBAR_KEYS: usize = 10;
+
+/// How long a sent message may go unconfirmed before it is flagged.
+pub const DEFAULT_ACK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Entries kept in each of the events, output and message logs; older ones
+/// are dropped.
+pub const DEFAULT_MAX_LOG_LINES: usize = 10_000;
+
+/// Which incoming connection requests are accepted without asking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AutoAccept {
+    /// Every request is left for `/acceptConnection`.
+    #[default]
+    Off,
+    /// Requests from users already in the known-users store.
+    Contacts,
+    /// Requests from users verified with `/verify`.
+    Verified,
+    /// Every request.
+    All,
+}
+
+impl AutoAccept {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(AutoAccept::Off),
+            "contacts" => Some(AutoAccept::Contacts),
+            "verified" => Some(AutoAccept::Verified),
+            "all" => Some(AutoAccept::All),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AutoAccept::Off => "off",
+            AutoAccept::Contacts => "contacts",
+            AutoAccept::Verified => "verified",
+            AutoAccept::All => "all",
+        }
+    }
+}
+
+/// Delivery state of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Stored by the node, awaiting confirmation.
+    Pending,
+    /// The peer's client sent a receipt for it.
+    Delivered,
+    /// No confirmation arrived within the ack timeout.
+    Unconfirmed,
+    /// Delivered to us by a peer.
+    Received,
+}
+
+impl Delivery {
+    pub fn icon(self) -> &'static str {
+        match self {
+            Delivery::Pending => "⏳",
+            Delivery::Delivered => "✓",
+            Delivery::Unconfirmed => "⚠",
+            Delivery::Received => RECEIVED_MARK,
+        }
+    }
+}
+
+/// A message sent or received during this session.
+#[derive(Debug, Clone)]
+pub struct MessageEntry {
+    pub from_id: String,
+    pub to_id: String,
+    /// Nick of the other side.
+    pub nick: String,
+    pub plugin_type: String,
+    pub body: serde_json::Value,
+    /// Hash returned by the node when the message was stored, or computed
+    /// locally for a received one.
+    pub hash: String,
+    /// When we sent it, or when it arrived; times the ack timeout.
+    pub sent_at: Instant,
+    /// Wall-clock time of the same moment, as shown in `/messages`.
+    pub at: SystemTime,
+    pub delivery: Delivery,
+    /// How far the sender's clock was off, in seconds (positive = ahead),
+    /// when implausibly far from ours.
+    pub skew: Option<i64>,
+}
+
+/// Leaders of a sent and a received message's line in `/messages`.
+pub const SENT_MARK: &str = "→";
+pub const RECEIVED_MARK: &str = "←";
+
+impl MessageEntry {
+    /// Direction, then delivery state for sent messages: `→ ⏳` or `←`.
+    pub fn marker(&self) -> String {
+        match self.delivery {
+            Delivery::Received => RECEIVED_MARK.to_string(),
+            delivery => format!("{} {}", SENT_MARK, delivery.icon()),
+        }
+    }
+
+    /// Id of the other side of the conversation.
+    pub fn peer_id(&self) -> &str {
+        if self.delivery == Delivery::Received { &self.from_id } else { &self.to_id }
+    }
+}
+
+/// A connection we just initiated, watched until it is established or the
+/// wait runs out.
+#[derive(Debug, Clone)]
+pub struct ConnectWait {
+    pub from_id: String,
+    pub to_id: String,
+    pub nick: String,
+    pub since: Instant,
+}
+
+/// A `/ping` sent and not yet answered.
+#[derive(Debug, Clone)]
+pub struct PendingPing {
+    /// Echoed back in the pong, to tell replies to different pings apart.
+    pub nonce: String,
+    pub to_id: String,
+    pub nick: String,
+    pub since: Instant,
+}
+
+/// A stored record that could not be read.
+#[derive(Debug, Clone)]
+pub struct StorageIssue {
+    /// Kind of record, e.g. "connection" or "user".
+    pub record: &'static str,
+    pub id: String,
+    pub error: String,
+}
+
+/// Events filter for `/watch`: one peer, matched by nick or id.
+#[derive(Debug, Clone)]
+pub struct Watch {
+    pub nick: String,
+    pub id: Option<String>,
+}
+
+impl Watch {
+    /// Whether an event line mentions the watched peer. Ids are usually
+    /// truncated in event text, so an 8-character prefix is enough.
+    pub fn matches(&self, line: &str) -> bool {
+        if line.to_lowercase().contains(&self.nick.to_lowercase()) {
+            return true;
+        }
+        match &self.id {
+            Some(id) => line.contains(&id[..id.len().min(8)]),
+            None => false,
+        }
+    }
+}
+
+/// What the content area is currently showing; drives per-view styling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewKind {
+    Info,
+    Help,
+    Events,
+    Console,
+    /// Events filtered to one peer by `/watch`.
+    Watch,
+    Messages,
+    Peers,
+    /// Live network peers grouped by peer id (`/netpeers`).
+    NetPeers,
+    Users,
+    Connections,
+    Error,
+    /// Key to the colours and icons (`/legend`).
+    Legend,
+}
+
+impl ViewKind {
+    /// Infer the view kind from a content title.
+    fn for_title(title: &str) -> Self {
+        match title {
+            "Help" => ViewKind::Help,
+            "Events" => ViewKind::Events,
+            "Console" => ViewKind::Console,
+            t if t.starts_with("Watch:") => ViewKind::Watch,
+            "Message" | "Messages" => ViewKind::Messages,
+            "Peers" => ViewKind::Peers,
+            "Network Peers" => ViewKind::NetPeers,
+            "User" | "Users" | "Nick" => ViewKind::Users,
+            t if t.starts_with("Connection") || t.ends_with("Connection") => ViewKind::Connections,
+            "Error" => ViewKind::Error,
+            "Legend" => ViewKind::Legend,
+            _ => ViewKind::Info,
+        }
+    }
+}
+
+/// A run of content lines that can be collapsed under a single header.
+#[derive(Debug, Clone)]
+pub struct Fold {
+    /// Index of the first folded line in `content_lines`.
+    pub start: usize,
+    pub len: usize,
+    pub label: String,
+    /// What the folded lines are, for the header count ("events", …).
+    pub unit: &'static str,
+    pub open: bool,
+}
+
+/// A view put aside when another replaced it, brought back by `/tab` as it
+/// was left.
+#[derive(Debug, Clone)]
+pub struct Tab {
+    pub lines: Vec<String>,
+    pub times: Vec<Option<SystemTime>>,
+    pub folds: Vec<Fold>,
+    pub scroll: u16,
+    /// The view command that produced it, so Ctrl+R still refreshes it.
+    pub command: String,
+}
+
+/// Markers in front of an open and a closed fold header.
+pub const FOLD_OPEN: &str = "▾";
+pub const FOLD_CLOSED: &str = "▸";
+
+/// One row of the content area once folds are applied.
+#[derive(Debug, Clone, Copy)]
+pub enum DisplayLine<'a> {
+    /// A line of an earlier output, kept above the current one by `/scrollback`.
+    History(&'a str),
+    /// The content line at this index in `content_lines`.
+    Text(usize, &'a str),
+    /// Header of the fold at this index in `content_folds`.
+    Fold(usize),
+}
+
+/// Geometry of the content pane as of the last draw, used for mouse
+/// hit-testing and scroll limits.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ContentGeometry {
+    /// The whole content block, borders included.
+    pub area: Rect,
+    /// Column and rows the scrollbar occupies (the right border).
+    pub scrollbar: Rect,
+    /// Number of lines that fit inside the borders.
+    pub visible: usize,
+    /// Number of lines in the current view.
+    pub total: usize,
+}
+
+impl ContentGeometry {
+    /// Largest useful `content_scroll` value.
+    pub fn max_scroll(&self) -> usize {
+        self.total.saturating_sub(self.visible)
+    }
+}
+
+/// Where `/copy` puts text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardMode {
+    /// The system clipboard, through the display server.
+    System,
+    /// An OSC 52 escape to the terminal, which sets the clipboard of the
+    /// machine the terminal runs on (so it works over SSH) if it allows it.
+    Osc52,
+}
+
+impl ClipboardMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "system" => Some(ClipboardMode::System),
+            "osc52" => Some(ClipboardMode::Osc52),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ClipboardMode::System => "system",
+            ClipboardMode::Osc52 => "osc52",
+        }
+    }
+}
+
+/// How timestamps are shown next to events and messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeDisplay {
+    /// "just now", "2m ago", … recomputed on every draw.
+    Relative,
+    /// Wall-clock time of day, in the local time zone.
+    Absolute,
+    Off,
+}
+
+impl TimeDisplay {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "relative" => Some(TimeDisplay::Relative),
+            "absolute" => Some(TimeDisplay::Absolute),
+            "off" => Some(TimeDisplay::Off),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TimeDisplay::Relative => "relative",
+            TimeDisplay::Absolute => "absolute",
+            TimeDisplay::Off => "off",
+        }
+    }
+
+    /// Label for `at`, or `None` when timestamps are off.
+    pub fn label(self, at: SystemTime) -> Option<String> {
+        match self {
+            TimeDisplay::Off => None,
+            TimeDisplay::Relative => {
+                let secs = at.elapsed().unwrap_or_default().as_secs();
+                Some(match secs {
+                    0..10 => "just now".to_string(),
+                    10..60 => format!("{}s ago", secs),
+                    60..3600 => format!("{}m ago", secs / 60),
+                    3600..86400 => format!("{}h ago", secs / 3600),
+                    _ => format!("{}d ago", secs / 86400),
+                })
+            }
+            TimeDisplay::Absolute => Some(DateTime::<Local>::from(at).format("%H:%M:%S").to_string()),
+        }
+    }
+}
+
+/// When a connection was first seen pending and first seen established.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectionTimes {
+    pub created: Option<SystemTime>,
+    pub accepted: Option<SystemTime>,
+}
+
+/// State of the command palette overlay (Ctrl+P).
+#[derive(Debug, Clone, Default)]
+pub struct Palette {
+    pub filter: String,
+    /// Index into the filtered command list.
+    pub selected: usize,
+}
+
+/// A `/find` in the current view.
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub term: String,
+    /// Indices into `content_lines` of the matching lines, in order.
+    pub matches: Vec<usize>,
+    /// Index into `matches` of the match last jumped to.
+    pub current: usize,
+}
+
+/// Width of one sparkline bucket; twelve of them cover the last minute.
+const ACTIVITY_BUCKET: Duration = Duration::from_secs(5);
+const ACTIVITY_BUCKETS: usize = 12;
+
+/// Message counts per interval over the last minute, oldest first, for the
+/// header sparkline.
+#[derive(Debug, Clone)]
+pub struct Activity {
+    buckets: [u32; ACTIVITY_BUCKETS],
+    /// When the newest bucket started.
+    since: Instant,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self { buckets: [0; ACTIVITY_BUCKETS], since: Instant::now() }
+    }
+}
+
+impl Activity {
+    /// Count one message in the current interval.
+    pub fn record(&mut self) {
+        self.buckets[ACTIVITY_BUCKETS - 1] += 1;
+    }
+
+    /// Shift in empty buckets for every interval that has passed.
+    pub fn tick(&mut self) {
+        while self.since.elapsed() >= ACTIVITY_BUCKET {
+            self.buckets.rotate_left(1);
+            self.buckets[ACTIVITY_BUCKETS - 1] = 0;
+            self.since += ACTIVITY_BUCKET;
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.buckets.iter().sum()
+    }
+
+    /// One block character per bucket, scaled to the busiest one.
+    pub fn sparkline(&self) -> String {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let max = self.buckets.iter().copied().max().unwrap_or(0).max(1);
+        self.buckets
+            .iter()
+            .map(|&n| match n {
+                0 => BARS[0],
+                n => BARS[1 + ((n * 7 - 1) / max).min(6) as usize],
+            })
+            .collect()
+    }
+}
+
+/// Sends awaiting a node reply beyond which the node is considered backed up.
+pub const SEND_QUEUE_WARN: usize = 8;
+
+/// Number of `StoreMessage` commands sent to the node and not yet answered.
+/// Shared, so a reply handled on another task still settles its slot.
+#[derive(Debug, Clone, Default)]
+pub struct SendQueue(Arc<AtomicUsize>);
+
+impl SendQueue {
+    /// Count one send as in flight until the returned guard is dropped.
+    pub fn enter(&self) -> InFlight {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        InFlight(self.0.clone())
+    }
+
+    pub fn depth(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// One in-flight send; dropping it (reply, error or early return) releases it.
+pub struct InFlight(Arc<AtomicUsize>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 pub struct App {
     pub content_scroll: u16,
+    pub content_geometry: ContentGeometry,
+    /// A scrollbar thumb drag is in progress.
+    pub scrollbar_drag: bool,
     /// Lines currently displayed in the content area.
     pub content_lines: Vec<String>,
+    /// When each content line happened, for views that have times; shorter
+    /// than `content_lines` (or empty) where lines have none.
+    pub content_times: Vec<Option<SystemTime>>,
+    /// How `content_times` are drawn (`/set timestamps`).
+    pub time_display: TimeDisplay,
+    /// Where `/copy` puts text (`/set clipboard`).
+    pub clipboard_mode: ClipboardMode,
+    /// System clipboard handle, opened on the first `/copy` and kept so the
+    /// copied text stays available on platforms where it lives with it.
+    pub clipboard: Option<arboard::Clipboard>,
+    /// Collapsible runs within `content_lines`, ordered by `start`.
+    pub content_folds: Vec<Fold>,
     /// Title shown on the content block border.
     pub content_title: String,
+    pub content_kind: ViewKind,
+    /// The command line that produced the current view, if it came from one.
+    pub content_command: Option<String>,
+    /// Bumped on every `set_content`, so callers can tell whether a command
+    /// replaced the view.
+    pub content_version: u64,
+
+    /// The command palette, while it is open.
+    pub palette: Option<Palette>,
+    /// Whether the keybinding overlay (`?`) is open.
+    pub show_keys: bool,
+    /// The last `/find` in the current view.
+    pub search: Option<Search>,
+    /// The last command error, kept on the error line under the content until
+    /// dismissed (Ctrl+L) or replaced by the next error.
+    pub last_error: Option<String>,
 
+    /// Shown before the prompt input, followed by a space (`/set prompt`).
+    pub prompt_prefix: String,
     pub prompt_input: String,
+    /// Start typed input with `/` when the first key isn't one (`/set
+    /// autoSlash`). Pastes are never prefixed.
+    pub auto_slash: bool,
+    /// Cursor position in `prompt_input`, counted in chars.
+    pub prompt_cursor: usize,
     pub prompt_history: Vec<String>,
     /// Index into prompt_history while scrolling; None = live input.
     pub prompt_history_idx: Option<usize>,
 
     pub node_tx: Option<mpsc::Sender<FullNodeCommand>>,
     pub node_status: NodeStatus,
+    /// When the running node was started.
+    pub node_started: Option<Instant>,
+    /// Sender half of the inbox drained by the main loop; messages pushed
+    /// into it show up as received. For the node to hand over once it can
+    /// (receiving is not yet implemented in the library).
+    #[expect(dead_code, reason = "FullNode cannot take an inbox yet")]
+    pub inbox: mpsc::UnboundedSender<Message>,
+    /// Sender half of the node event channel drained by the main loop.
+    pub node_events: mpsc::UnboundedSender<NodeEvent>,
+    /// Background storage watcher, running while the node is.
+    pub watcher: Option<JoinHandle<()>>,
+    /// The node's libp2p peer id, once it has announced it.
+    pub local_peer_id: Option<String>,
     /// TCP port the node listens on (default 51030).
     pub listen_port: u16,
+    /// Move on to the next port when the listen port is taken at start.
+    pub port_fallback: bool,
+    /// Storage directory for the network storage layer; `None` = default.
+    pub storage_dir: Option<PathBuf>,
 
+    /// Id of the local user, cached once known.
+    pub local_id: Option<String>,
     pub peers: Vec<String>,
+    /// Re-render the peers view as peers come and go (`/peers follow`).
+    pub peers_follow: bool,
+    /// When `peers` was last re-read from storage while following.
+    pub peers_loaded: Option<Instant>,
+    /// Peers seen live on the network this session, with every address each
+    /// was seen on. Keyed by peer id so one peer on several transports is
+    /// still one entry.
+    pub live_peers: BTreeMap<String, Vec<String>>,
     pub users: Vec<User>,
     pub connections: Vec<Connection>,
-    pub messages: Vec<String>,
+    /// Records skipped on the last load because they could not be read.
+    pub storage_issues: Vec<StorageIssue>,
+    /// Why the storage directory is treated as read-only, after a write to
+    /// it failed. Commands that write are refused until `/storage recheck`.
+    pub read_only: Option<String>,
+    /// The connection `/connection` is waiting on, if any.
+    pub connect_wait: Option<ConnectWait>,
+    /// Pings awaiting their echo.
+    pub pings: Vec<PendingPing>,
+    /// `(from_id, to_id)` of the connection shown in the detail view, if any.
+    pub connection_view: Option<(String, String)>,
+    /// When this session saw each connection, keyed by `(from_id, to_id)`.
+    /// Stored records carry no timestamps of their own.
+    pub connection_times: HashMap<(String, String), ConnectionTimes>,
+    /// Messages sent this session, oldest first, capped at `max_log_lines`.
+    pub messages: VecDeque<MessageEntry>,
+    /// Print ids and hashes in full rather than shortened (`/ids`).
+    pub show_full_ids: bool,
+    /// `(nick, id)` of the peer `/messages` is narrowed to, if any.
+    pub messages_peer: Option<(String, String)>,
+    /// Lowercased nick → user id, filled by `/users` and by lookups; cleared
+    /// by `/nick`.
+    pub nick_cache: HashMap<String, String>,
+    /// Messages exchanged with each remote user, across sessions.
+    pub message_counts: MessageCounts,
+    /// Last implausible clock offset seen from each peer, in seconds.
+    pub clock_skew: HashMap<String, i64>,
+    /// Messages handed to the node and still awaiting its reply.
+    pub send_queue: SendQueue,
+    /// How long sent messages wait for confirmation before being flagged.
+    pub ack_timeout: Duration,
+    /// Peers verified out of band via `/verify`.
+    pub trust: TrustStore,
+    /// Policy for incoming connection requests.
+    pub auto_accept: AutoAccept,
+    /// Recent message counts for the header sparkline.
+    pub activity: Activity,
+    /// Whether the header shows the activity sparkline.
+    pub show_sparkline: bool,
+    /// Commands bound to F1–F10, indexed from F1.
+    pub quickbar: Vec<Option<String>>,
+    /// Whether the quick-bar row is drawn above the prompt.
+    pub show_quickbar: bool,
+    /// Show live events in a pane beside the content (`/layout split`).
+    pub split_view: bool,
+    /// Keep earlier command outputs above the current one (`/scrollback`).
+    pub scrollback: bool,
+    /// Earlier outputs, each under a line naming its command. Capped at
+    /// `max_log_lines`.
+    pub scrollback_lines: Vec<String>,
+    /// Views put aside, keyed by their title.
+    pub tabs: HashMap<String, Tab>,
+    /// Titles of every view kept as a tab, in the order they were first
+    /// opened; `/tab <n>` and Alt+n count from 1 in this order.
+    pub tab_order: Vec<String>,
+    /// Set while a command runs in scrollback mode: the next `set_content`
+    /// moves the current output into `scrollback_lines` instead of dropping it.
+    pub archive_next: bool,
+
+    /// Node events in chronological order (shown by /events), capped at
+    /// `max_log_lines`.
+    pub events: VecDeque<String>,
+    /// When each entry of `events` was recorded.
+    pub event_times: VecDeque<SystemTime>,
+    /// Peer whose events are followed live in the watch view.
+    pub watch: Option<Watch>,
+    /// Show `/events` and `/watch` newest-first.
+    pub events_desc: bool,
+    /// Command output log (shown by /console), capped at `max_log_lines`.
+    pub output: VecDeque<String>,
+    /// When each entry of `output` was recorded.
+    pub output_times: VecDeque<SystemTime>,
+    /// Most entries kept in each of `events`, `output` and `messages`.
+    pub max_log_lines: usize,
+    /// Bumped whenever a line is added to `events` or `output`, so the main
+    /// loop can tell whether a tick changed anything.
+    pub log_version: u64,
+    /// Interval of the main loop's periodic checks (`/set tick`).
+    pub tick_interval: Duration,
 
-    /// All node events in chronological order (shown by /events).
-    pub events: Vec<String>,
-    /// Command output log (shown by /console).
-    pub output: Vec<String>,
+    pub theme: Theme,
 
+    /// Esc and Ctrl+C don't quit; only `/quit` or Ctrl+Alt+Q do.
+    pub locked: bool,
     pub should_quit: bool,
+    /// A quit was asked for while the node runs; the next one confirms it.
+    pub quit_armed: bool,
+
+    /// The settings the app was started with, and where each came from.
+    pub startup: Config,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn with_config(
+        config: Config,
+        node_events: mpsc::UnboundedSender<NodeEvent>,
+        inbox: mpsc::UnboundedSender<Message>,
+    ) -> Self {
+        let startup = config.clone();
         let welcome = vec![
             "Welcome to Accord!".to_string(),
-            "Starting the P2P node…".to_string(),
+            if config.autostart {
+                "Starting the P2P node…".to_string()
+            } else {
+                "Use /startNode to start the P2P node.".to_string()
+            },
             "Type /help to see all available commands.".to_string(),
         ];
         Self {
             content_scroll: 0,
+            content_geometry: ContentGeometry::default(),
+            scrollbar_drag: false,
             content_lines: welcome.clone(),
+            content_times: Vec::new(),
+            time_display: TimeDisplay::Relative,
+            clipboard_mode: ClipboardMode::System,
+            clipboard: None,
+            content_folds: Vec::new(),
             content_title: " Accord ".to_string(),
+            content_kind: ViewKind::Info,
+            content_command: None,
+            content_version: 0,
+            palette: None,
+            show_keys: false,
+            search: None,
+            last_error: None,
+            prompt_prefix: DEFAULT_PROMPT_PREFIX.to_string(),
             prompt_input: String::new(),
+            auto_slash: true,
+            prompt_cursor: 0,
             prompt_history: Vec::new(),
             prompt_history_idx: None,
             node_tx: None,
             node_status: NodeStatus::Stopped,
-            listen_port: 51030,
+            node_started: None,
+            inbox,
+            node_events,
+            watcher: None,
+            local_peer_id: None,
+            listen_port: config.listen_port,
+            port_fallback: true,
+            storage_dir: config.storage_dir,
+            local_id: None,
             peers: Vec::new(),
+            peers_follow: false,
+            peers_loaded: None,
+            live_peers: BTreeMap::new(),
             users: Vec::new(),
             connections: Vec::new(),
-            messages: Vec::new(),
-            events: welcome,
-            output: Vec::new(),
+            storage_issues: Vec::new(),
+            read_only: None,
+            connect_wait: None,
+            pings: Vec::new(),
+            connection_view: None,
+            connection_times: HashMap::new(),
+            messages: VecDeque::new(),
+            show_full_ids: false,
+            messages_peer: None,
+            nick_cache: HashMap::new(),
+            message_counts: MessageCounts::load(),
+            clock_skew: HashMap::new(),
+            send_queue: SendQueue::default(),
+            ack_timeout: DEFAULT_ACK_TIMEOUT,
+            trust: TrustStore::load(),
+            auto_accept: AutoAccept::Off,
+            activity: Activity::default(),
+            show_sparkline: true,
+            quickbar: config.quickbar,
+            show_quickbar: true,
+            split_view: false,
+            scrollback: false,
+            scrollback_lines: Vec::new(),
+            archive_next: false,
+            tabs: HashMap::new(),
+            tab_order: Vec::new(),
+            event_times: VecDeque::from(vec![SystemTime::now(); welcome.len()]),
+            events: VecDeque::from(welcome),
+            watch: None,
+            events_desc: config.events_desc,
+            output: VecDeque::new(),
+            output_times: VecDeque::new(),
+            max_log_lines: DEFAULT_MAX_LOG_LINES,
+            log_version: 0,
+            tick_interval: config.tick,
+            theme: config.theme,
+            locked: config.locked,
             should_quit: false,
+            quit_armed: false,
+            startup,
         }
     }
 
+    /// Storage directory to pass to the storage layer.
+    pub fn storage(&self) -> Option<&Path> {
+        self.storage_dir.as_deref()
+    }
+
     /// Replace the content area with new lines and a title.
     pub fn set_content(&mut self, title: impl Into<String>, lines: Vec<String>) {
-        self.content_title = format!(" {} ", title.into());
+        if std::mem::take(&mut self.archive_next) {
+            self.archive_content();
+        }
+        let title = title.into();
+        self.content_kind = ViewKind::for_title(&title);
+        self.content_title = format!(" {} ", title);
         self.content_lines = lines;
-        self.content_scroll = 0;
+        self.content_times.clear();
+        self.content_folds.clear();
+        self.search = None;
+        self.content_scroll = self.content_top();
+        self.connection_view = None;
+        self.content_version += 1;
+    }
+
+    /// Move the current output to the end of the scrollback, under the
+    /// command (or else the title) it came from.
+    fn archive_content(&mut self) {
+        if self.content_lines.is_empty() {
+            return;
+        }
+        let source = self.content_command.clone().unwrap_or_else(|| self.content_title.trim().to_string());
+        self.scrollback_lines.push(format!("── {} ──", source));
+        self.scrollback_lines.append(&mut self.content_lines);
+        self.scrollback_lines.push(String::new());
+        let excess = self.scrollback_lines.len().saturating_sub(self.max_log_lines);
+        self.scrollback_lines.drain(..excess);
+    }
+
+    /// Scroll position of the current output's first line, below any
+    /// earlier outputs in the scrollback.
+    pub fn content_top(&self) -> u16 {
+        u16::try_from(self.scrollback_lines.len()).unwrap_or(u16::MAX)
+    }
+
+    /// Scroll position that brings the current output's last line into view.
+    pub fn content_end(&self) -> u16 {
+        u16::try_from(self.scrollback_lines.len() + self.content_lines.len()).unwrap_or(u16::MAX)
+    }
+
+    /// Cheap fingerprint of what is on screen; it differs after anything a
+    /// draw would show has changed.
+    pub fn screen_version(&self) -> (u64, u64, usize, u32) {
+        (self.content_version, self.log_version, self.content_lines.len(), self.activity.total())
+    }
+
+    /// Whether the screen changes with time alone, so idle ticks still need
+    /// a redraw: the sparkline is moving, or relative times are on show.
+    pub fn animating(&self) -> bool {
+        (self.show_sparkline && self.activity.total() > 0)
+            || (self.time_display == TimeDisplay::Relative && self.content_times.iter().any(Option::is_some))
+    }
+
+    /// The content rows to draw: the scrollback, then `content_lines` with
+    /// closed folds collapsed to their header.
+    pub fn display_lines(&self) -> Vec<DisplayLine<'_>> {
+        let mut rows = Vec::with_capacity(self.scrollback_lines.len() + self.content_lines.len());
+        rows.extend(self.scrollback_lines.iter().map(|line| DisplayLine::History(line)));
+        let mut folds = self.content_folds.iter().enumerate().peekable();
+        let mut i = 0;
+        while i < self.content_lines.len() {
+            if let Some((idx, fold)) = folds.next_if(|(_, f)| f.start == i) {
+                rows.push(DisplayLine::Fold(idx));
+                if !fold.open {
+                    i += fold.len;
+                    continue;
+                }
+            }
+            rows.push(DisplayLine::Text(i, &self.content_lines[i]));
+            i += 1;
+        }
+        rows
+    }
+
+    /// Like `set_content`, but styled as an error. The first line also goes
+    /// to the error line, so it outlives the view.
+    pub fn set_error(&mut self, title: impl Into<String>, lines: Vec<String>) {
+        self.last_error = lines.first().cloned();
+        self.set_content(title, lines);
+        self.content_kind = ViewKind::Error;
+    }
+
+    /// Replace the recorded issues for one kind of record.
+    pub fn set_storage_issues(&mut self, record: &str, issues: Vec<StorageIssue>) {
+        self.storage_issues.retain(|i| i.record != record);
+        self.storage_issues.extend(issues);
+    }
+
+    /// Report a problem on the error line, and in the output log, without
+    /// replacing the view it is about.
+    pub fn push_error(&mut self, line: impl Into<String>) {
+        let line = line.into();
+        self.last_error = Some(line.clone());
+        self.push_output(line);
     }
 
     /// Append a line to the events log.
     pub fn push_event(&mut self, line: impl Into<String>) {
-        self.events.push(line.into());
+        let line = line.into();
+        if self.content_kind == ViewKind::Watch && self.watch.as_ref().is_some_and(|w| w.matches(&line)) {
+            // Follow the newest line, at whichever end it is.
+            if self.events_desc {
+                self.content_lines.insert(0, line.clone());
+                self.content_times.insert(0, Some(SystemTime::now()));
+                self.content_lines.truncate(self.max_log_lines);
+                self.content_times.truncate(self.max_log_lines);
+                self.content_scroll = self.content_top();
+            } else {
+                self.content_lines.push(line.clone());
+                self.content_times.push(Some(SystemTime::now()));
+                if self.content_lines.len() > self.max_log_lines {
+                    self.content_lines.remove(0);
+                    self.content_times.remove(0);
+                }
+                self.content_scroll = self.content_end();
+            }
+        }
+        self.events.push_back(line);
+        self.event_times.push_back(SystemTime::now());
+        self.log_version += 1;
+        self.trim_logs();
     }
 
     /// Append a line to the console output log.
     pub fn push_output(&mut self, line: impl Into<String>) {
-        self.output.push(line.into());
+        self.output.push_back(line.into());
+        self.output_times.push_back(SystemTime::now());
+        self.log_version += 1;
+        self.trim_logs();
+    }
+
+    /// Record a sent message.
+    pub fn push_message(&mut self, message: MessageEntry) {
+        self.messages.push_back(message);
+        self.trim_logs();
+    }
+
+    /// Drop the oldest log entries beyond `max_log_lines`.
+    pub fn trim_logs(&mut self) {
+        let max = self.max_log_lines;
+        let excess = self.events.len().saturating_sub(max);
+        self.events.drain(..excess);
+        self.event_times.drain(..excess);
+        let excess = self.output.len().saturating_sub(max);
+        self.output.drain(..excess);
+        self.output_times.drain(..excess);
+        let excess = self.messages.len().saturating_sub(max);
+        self.messages.drain(..excess);
     }
 
+    /// Replace the prompt input, with the cursor at its end.
+    pub fn set_prompt(&mut self, input: impl Into<String>) {
+        self.prompt_input = input.into();
+        self.prompt_cursor = self.prompt_input.chars().count();
+    }
+
+    /// The prompt input left of the cursor.
+    pub fn prompt_before_cursor(&self) -> &str {
+        &self.prompt_input[..self.prompt_cursor_byte()]
+    }
+
+    /// Insert a char at the cursor and move past it.
+    pub fn prompt_insert(&mut self, c: char) {
+        let at = self.prompt_cursor_byte();
+        self.prompt_input.insert(at, c);
+        self.prompt_cursor += 1;
+    }
+
+    /// Insert a string at the cursor and move past it.
+    pub fn prompt_insert_str(&mut self, text: &str) {
+        let at = self.prompt_cursor_byte();
+        self.prompt_input.insert_str(at, text);
+        self.prompt_cursor += text.chars().count();
+    }
+
+    /// Delete the character left of the cursor, together with any combining
+    /// marks on it, so `é` written as `e` + U+0301 goes in one keypress.
+    pub fn prompt_backspace(&mut self) {
+        let end = self.prompt_cursor_byte();
+        let mut start = end;
+        for (i, c) in self.prompt_input[..end].char_indices().rev() {
+            start = i;
+            self.prompt_cursor -= 1;
+            if c.width() != Some(0) {
+                break;
+            }
+        }
+        self.prompt_input.replace_range(start..end, "");
+    }
+
+    /// Delete everything left of the cursor.
+    pub fn prompt_kill_to_start(&mut self) {
+        let end = self.prompt_cursor_byte();
+        self.prompt_input.replace_range(..end, "");
+        self.prompt_cursor = 0;
+    }
+
+    /// Delete the word left of the cursor, and the whitespace between it and
+    /// the cursor.
+    pub fn prompt_kill_word(&mut self) {
+        let end = self.prompt_cursor_byte();
+        let before = self.prompt_input[..end].trim_end();
+        let start = before
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        self.prompt_cursor -= self.prompt_input[start..end].chars().count();
+        self.prompt_input.replace_range(start..end, "");
+    }
+
+    /// Move the cursor by `delta` chars, within the input.
+    pub fn prompt_move(&mut self, delta: isize) {
+        let len = self.prompt_input.chars().count();
+        self.prompt_cursor = self.prompt_cursor.saturating_add_signed(delta).min(len);
+    }
+
+    fn prompt_cursor_byte(&self) -> usize {
+        self.prompt_input
+            .char_indices()
+            .nth(self.prompt_cursor)
+            .map_or(self.prompt_input.len(), |(i, _)| i)
+    }
+}
+
+impl Default for App {
+    /// An app with default settings whose node events and inbox go nowhere;
+    /// useful when no main loop is draining them.
+    fn default() -> Self {
+        Self::with_config(Config::default(), mpsc::unbounded_channel().0, mpsc::unbounded_channel().0)
+    }
 }
@@ -5,11 +5,33 @@ use accord_network::{
         list_connections, list_known_users, load_connection, load_known_user, load_local_user,
         load_peers, save_local_user,
     },
-    Connection, FullNode, FullNodeCommand, User, UserMeta,
+    Connection, FullNode, FullNodeCommand, Message, User, UserMeta,
 };
-use tokio::sync::oneshot;
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    io::Write,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::{mpsc, oneshot};
 
-use crate::app::{App, NodeStatus};
+use crate::{
+    app::{
+        App, AutoAccept, ClipboardMode, ConnectWait, Delivery, DisplayLine, Fold, PendingPing, Search, Tab, TimeDisplay,
+        MessageEntry, NodeStatus, StorageIssue, ViewKind, Watch,
+        DEFAULT_PROMPT_PREFIX, FOLD_CLOSED, FOLD_OPEN, SENT_MARK, QUICKBAR_KEYS, SEND_QUEUE_WARN,
+    },
+    config::{self, Source},
+    node,
+    theme::SWATCH,
+    trust::{self, KeyStatus, TrustStore},
+    ui::HEADER_DOT,
+};
 
 fn listen_addr(port: u16) -> String {
     format!("/ip4/0.0.0.0/tcp/{}", port)
@@ -20,153 +42,1638 @@ pub async fn execute(app: &mut App, raw: &str) -> Result<()> {
     if input.is_empty() {
         return Ok(());
     }
+    let input = &canonical_input(input);
 
+    if split_command(input).0 != "/quit" {
+        app.quit_armed = false;
+    }
+    let version = app.content_version;
+    let leaving = current_tab(app);
+    // In scrollback mode the output this command replaces stays above it.
+    app.archive_next = app.scrollback;
+    let result = dispatch(app, input).await;
+    app.archive_next = false;
+    if let Some((title, tab)) = leaving
+        && app.content_title.trim() != title
+    {
+        app.tabs.insert(title, tab);
+    }
+    if let Err(e) = result {
+        // A node that died leaves its channel closed: every send fails with
+        // "Node channel closed" while the header still says it runs.
+        if node_died(app) {
+            return Err(anyhow!("The node stopped unexpectedly. Run /startNode to start it again."));
+        }
+        return Err(e);
+    }
+    // A refresh re-runs the stored command, which stays the one to show;
+    // switching tabs brings back the tab's own.
     let (cmd, rest) = split_command(input);
+    if app.content_version != version && cmd != "/refresh" && !(cmd == "/tab" && !rest.is_empty()) {
+        app.content_command = Some(input.to_string());
+    }
+    if let Some(title) = current_tab(app).map(|(title, _)| title)
+        && !app.tab_order.contains(&title)
+    {
+        app.tab_order.push(title);
+    }
+    Ok(())
+}
+
+/// The current view as a tab, if it is one worth keeping: non-empty and
+/// produced by a view command, so it can be refreshed when brought back.
+/// The tab list itself is not one.
+fn current_tab(app: &App) -> Option<(String, Tab)> {
+    let command = app.content_command.clone().filter(|cmd| is_view_command(cmd) && cmd != "/tab")?;
+    if app.content_lines.is_empty() {
+        return None;
+    }
+    let tab = Tab {
+        lines: app.content_lines.clone(),
+        times: app.content_times.clone(),
+        folds: app.content_folds.clone(),
+        scroll: app.content_scroll,
+        command,
+    };
+    Some((app.content_title.trim().to_string(), tab))
+}
+
+/// Short forms accepted in place of a command name.
+const ALIASES: &[(&str, &str)] = &[
+    ("/q", "/quit"),
+    ("/h", "/help"),
+    ("/msg", "/message"),
+    ("/conn", "/connection"),
+];
+
+/// `input` with its command name spelled as in `COMMANDS`: aliases expanded
+/// and case ignored. Arguments are left untouched; unknown names too.
+fn canonical_input(input: &str) -> String {
+    let (cmd, rest) = split_command(input);
+    let name = ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(cmd))
+        .map(|&(_, name)| name)
+        .or_else(|| command_names().into_iter().find(|name| name.eq_ignore_ascii_case(cmd)))
+        .unwrap_or(cmd);
+    if rest.is_empty() { name.to_string() } else { format!("{} {}", name, rest) }
+}
+
+async fn dispatch(app: &mut App, input: &str) -> Result<()> {
+    let (cmd, rest) = split_command(input);
+    let (rest, dry_run) = strip_dry_run(rest);
+    if dry_run && !DRY_RUN_COMMANDS.contains(&cmd) {
+        app.set_error("Dry Run", vec![format!(
+            "{} does not support --dry-run. Supported: {}.", cmd, DRY_RUN_COMMANDS.join(", ")
+        )]);
+        return Ok(());
+    }
 
     match cmd {
         "/help" => cmd_help(app),
+        "/legend" => cmd_legend(app),
+        "/refresh" => cmd_refresh(app).await?,
         "/quit" => cmd_quit(app),
-        "/events" => cmd_events(app),
+        "/events" => cmd_events(app, rest),
         "/console" => cmd_console(app),
-        "/messages" => cmd_messages(app),
+        "/history" => cmd_history(app, rest),
+        "/fold" => cmd_fold(app, rest),
+        "/find" => cmd_find(app, rest),
+        "/save" => cmd_save(app, rest),
+        "/export" => cmd_export(app, rest),
+        "/clear" => cmd_clear(app),
+        "/layout" => cmd_layout(app, rest),
+        "/scrollback" => cmd_scrollback(app, rest),
+        "/tab" => cmd_tab(app, rest),
+        "/copy" => cmd_copy(app, rest),
+        "/ids" => cmd_ids(app, rest).await?,
+        "/watch" => cmd_watch(app, rest),
+        "/unwatch" => cmd_unwatch(app),
+        "/messages" => cmd_messages(app, rest),
         "/startNode" => cmd_start_node(app).await?,
         "/stopNode" => cmd_stop_node(app).await?,
         "/restartNode" => cmd_restart_node(app).await?,
         "/port" => cmd_port(app, rest).await?,
         "/sync" => cmd_sync(app),
-        "/peers" => cmd_peers(app)?,
+        "/addrs" => cmd_addrs(app),
+        "/peers" => cmd_peers(app, rest)?,
+        "/netpeers" => cmd_net_peers(app),
+        "/ping" => cmd_ping(app, rest).await?,
         "/nick" => cmd_nick(app, rest)?,
         "/user" => cmd_user(app, rest).await?,
-        "/users" => cmd_users(app).await?,
-        "/connection" => cmd_connection(app, rest).await?,
+        "/users" => cmd_users(app, rest).await?,
+        "/whoami" => cmd_whoami(app),
+        "/identity" => cmd_identity(app, rest),
+        "/connection" => cmd_connection(app, rest, dry_run).await?,
+        "/reconnect" => cmd_reconnect(app, rest).await?,
         "/connections" => cmd_connections(app)?,
+        "/connectionInfo" => cmd_connection_info(app, rest),
         "/connectionsPending" => cmd_connections_pending(app)?,
-        "/acceptConnection" => cmd_accept_connection(app, rest).await?,
-        "/declineConnection" => cmd_decline_connection(app, rest),
-        "/message" => cmd_message(app, rest).await?,
-        "/messagePlugin" => cmd_message_plugin(app, rest).await?,
+        "/connectionsEstablished" => cmd_connections_established(app)?,
+        "/acceptConnection" => cmd_accept_connection(app, rest, dry_run).await?,
+        "/declineConnection" => cmd_decline_connection(app, rest, dry_run),
+        "/cancelConnection" => cmd_cancel_connection(app, rest, dry_run),
+        "/message" => cmd_message(app, rest, dry_run).await?,
+        "/messagePlugin" => cmd_message_plugin(app, rest, dry_run).await?,
+        "/plugins" => cmd_plugins(app),
+        "/resend" => cmd_resend(app, rest, dry_run).await?,
+        "/reply" => cmd_reply(app, rest, dry_run).await?,
+        "/fingerprint" => cmd_fingerprint(app, rest).await?,
+        "/verify" => cmd_verify(app, rest).await?,
+        "/acceptKey" => cmd_accept_key(app, rest).await?,
+        "/stats" => cmd_stats(app),
+        "/status" => cmd_status(app, rest),
+        "/storage" => cmd_storage(app, rest),
+        "/repair" => cmd_repair(app),
+        "/set" => cmd_set(app, rest),
+        "/config" => cmd_config(app),
+        "/bind" => cmd_bind(app, rest),
+        "/unbind" => cmd_unbind(app, rest),
         _ => {
             let msg = format!("Unknown command: {}. Type /help for a list.", cmd);
             app.push_event(format!("[CMD] Unknown: {}", cmd));
-            show_lines(app, "Error", vec![msg]);
+            app.set_error("Error", vec![msg]);
         }
     }
 
     Ok(())
 }
 
+/// Re-run the command that produced the current view, keeping the reader's
+/// scroll position and open folds when the same view comes back.
+async fn cmd_refresh(app: &mut App) -> Result<()> {
+    let cmd = match app.content_command.clone() {
+        Some(cmd) if is_view_command(&cmd) => cmd,
+        Some(cmd) => {
+            app.push_error(format!("Not re-running {} — it changes state, not just the view.", cmd));
+            return Ok(());
+        }
+        None => {
+            app.push_error("This view did not come from a command.");
+            return Ok(());
+        }
+    };
+
+    let title = app.content_title.clone();
+    let scroll = app.content_scroll;
+    let open: Vec<(String, bool)> = app.content_folds.iter().map(|f| (f.label.clone(), f.open)).collect();
+    // The view is redrawn in place, not added to the scrollback again.
+    app.archive_next = false;
+    Box::pin(dispatch(app, &cmd)).await?;
+    if app.content_title == title {
+        app.content_scroll = scroll;
+        for fold in app.content_folds.iter_mut() {
+            if let Some((_, was_open)) = open.iter().find(|(label, _)| *label == fold.label) {
+                fold.open = *was_open;
+            }
+        }
+    }
+    app.push_output(format!("Refreshed {}.", cmd));
+    Ok(())
+}
+
+/// Commands that only display state, and so are safe to re-run with Ctrl+R.
+const VIEW_COMMANDS: &[&str] = &[
+    "/help",
+    "/legend",
+    "/clear",
+    "/events",
+    "/console",
+    "/watch",
+    "/messages",
+    "/peers",
+    "/netpeers",
+    "/users",
+    "/whoami",
+    "/addrs",
+    "/identity",
+    "/connections",
+    "/connectionsPending",
+    "/connectionsEstablished",
+    "/connectionInfo",
+    "/fingerprint",
+    "/stats",
+    "/plugins",
+    "/status",
+    "/storage",
+    "/config",
+];
+
+/// Whether re-running `input` would only refresh a view rather than change
+/// anything (send, store, restart, …).
+pub fn is_view_command(input: &str) -> bool {
+    let (cmd, rest) = split_command(input);
+    let (rest, dry_run) = strip_dry_run(rest);
+    dry_run
+        || VIEW_COMMANDS.contains(&cmd)
+        || (matches!(cmd, "/set" | "/bind" | "/history" | "/nick" | "/tab" | "/scrollback" | "/ids" | "/layout")
+            && rest.is_empty())
+}
+
+/// Commands that accept a trailing `--dry-run`: they validate and resolve
+/// everything, then stop short of sending anything to the node.
+const DRY_RUN_COMMANDS: &[&str] = &[
+    "/connection",
+    "/acceptConnection",
+    "/declineConnection",
+    "/cancelConnection",
+    "/message",
+    "/messagePlugin",
+    "/resend",
+    "/reply",
+];
+
+/// Strip a trailing `--dry-run` flag from the arguments.
+fn strip_dry_run(rest: &str) -> (&str, bool) {
+    match rest.strip_suffix("--dry-run") {
+        Some(head) if head.is_empty() || head.ends_with(' ') => (head.trim_end(), true),
+        _ => (rest, false),
+    }
+}
+
+/// Show what a command would have done, with a clear dry-run banner.
+fn show_dry_run(app: &mut App, title: &str, mut lines: Vec<String>) {
+    let mut out = vec!["Dry run — nothing was sent or changed.".to_string(), String::new()];
+    out.append(&mut lines);
+    if app.node_tx.is_none() {
+        out.push(String::new());
+        out.push("  Note: the node is not running, so the real command would fail. Use /startNode first.".to_string());
+    }
+    app.push_output(format!("Dry run: {}.", title));
+    app.set_content(title, out);
+}
+
 // ---------------------------------------------------------------------------
 // Help
 // ---------------------------------------------------------------------------
 
+/// Every command's usage and one-line summary, in `/help` order. Also feeds
+/// the command palette.
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("/startNode", "Start the P2P node"),
+    ("/stopNode", "Stop the P2P node"),
+    ("/restartNode", "Restart the P2P node"),
+    ("/port <port>", "Change listen port and restart node"),
+    ("/sync", "Note: sync is automatic"),
+    ("/addrs", "Show the addresses a peer can dial you on, with your peer id"),
+    ("/peers", "Show all known peers in content"),
+    ("/peers follow", "Show peers and keep the view updating live"),
+    ("/netpeers", "Show live network peers, one per peer id (click to expand)"),
+    ("/ping <nick>", "Check that a peer is alive and measure the round trip"),
+    ("/user", "Show local user (or create one) in content"),
+    ("/whoami", "Show your own id, key, port and node status"),
+    ("/nick [new_name]", "Show or change your display name"),
+    ("/users [sort:activity]", "Show all known users, optionally busiest first"),
+    ("/user <nick>", "Show a user by display name in content"),
+    ("/identity list", "List local identities"),
+    ("/connection <nick>", "Initiate a connection with a user"),
+    ("/reconnect <nick>", "Re-initiate a connection on record, e.g. after the peer was offline"),
+    ("/connections", "View all connections in content"),
+    ("/connectionInfo <nick>", "Show one connection in full: ids, names, state and key"),
+    ("/connectionsPending", "View pending connections in content"),
+    ("/connectionsEstablished", "View established connections: who you can message now"),
+    ("/cancelConnection <nick>", "Withdraw your own pending connection request"),
+    ("/acceptConnection <from_id> <their_pubkey>", "Accept an incoming connection"),
+    ("/declineConnection <connection_id>", "Decline someone's pending connection request"),
+    ("/message <nick> <body>", "Send a text message"),
+    ("/messagePlugin <nick> <type> <body>", "Send a plugin message"),
+    ("/plugins", "List the plugin types peers understand, with their body format"),
+    ("/messages [nick]", "Show all messages, or the conversation with one peer"),
+    ("/resend <hash>", "Resend an unconfirmed message"),
+    ("/reply <hash> <body>", "Reply to a message by its hash"),
+    ("/fingerprint [nick]", "Show your (or a peer's) key fingerprint"),
+    ("/verify <nick>", "Mark a peer's key as verified out of band"),
+    ("/acceptKey <nick>", "Accept a peer's changed public key"),
+    ("/events [--desc|--asc]", "Show all node events, newest last (or first)"),
+    ("/console", "Show all output in content"),
+    ("/history [n]", "List prompt history; with n, put entry n back in the prompt"),
+    ("/save [events|output] <path>", "Write the current view, or a whole log, to a text file"),
+    ("/export messages <path>", "Write the message history as JSON (.jsonl: one message per line)"),
+    ("/clear", "Empty the content pane (logs are kept)"),
+    ("/layout [split|single]", "Show live events beside the content, or the content alone"),
+    ("/scrollback [on|off]", "Keep earlier command outputs above the latest one"),
+    ("/tab [n|name]", "List the kept views, or switch back to one where you left it"),
+    ("/copy [line]", "Copy the view, or its line-th line, to the clipboard"),
+    ("/ids full|short", "Print ids and hashes in full, or shortened"),
+    ("/fold expand|collapse", "Open or close all folded sections (or click one)"),
+    ("/find [text]", "Find text in the current view; again or Ctrl+N for the next match"),
+    ("/watch <nick>", "Follow live events mentioning one peer"),
+    ("/unwatch", "Stop following a peer's events"),
+    ("/stats", "Show send queue, message and network counters"),
+    ("/status [--json]", "Show node health; --json prints it as one line of JSON"),
+    ("/storage [recheck]", "Show storage writability and unreadable records"),
+    ("/repair", "Help recover unreadable stored records"),
+    ("/set [<setting> <value>]", "Show or change a setting"),
+    ("/config", "Show startup settings and where each came from"),
+    ("/bind [F<n> <command>|F<n> F<m>]", "Show, set or swap quick-bar keys (F1–F10)"),
+    ("/unbind F<n>", "Clear a quick-bar key"),
+    ("/help", "Show all commands in content"),
+    ("/legend", "Show what each colour and icon means"),
+    ("/refresh", "Re-run the command behind the current view (Ctrl+R)"),
+    ("/quit", "Quit the TUI"),
+];
+
 fn cmd_help(app: &mut App) {
-    let lines: Vec<String> = [
-        "Available commands:",
-        "  /startNode                                   Start the P2P node",
-        "  /stopNode                                    Stop the P2P node",
-        "  /restartNode                                 Restart the P2P node",
-        "  /port <port>                                 Change listen port and restart node",
-        "  /sync                                        Note: sync is automatic",
-        "  /peers                                       Show all known peers in content",
-        "  /user                                        Show local user (or create one) in content",
-        "  /nick <new_name>                             Change your display name",
-        "  /users                                       Show all known users in content",
-        "  /user <nick>                                 Show a user by display name in content",
-        "  /connection <nick>                           Initiate a connection with a user",
-        "  /connections                                 View all connections in content",
-        "  /connectionsPending                          View pending connections in content",
-        "  /acceptConnection <from_id> <their_pubkey>   Accept an incoming connection",
-        "  /declineConnection <connection_id>           Decline a connection",
-        "  /message <nick> <body>                       Send a text message",
-        "  /messagePlugin <nick> <type> <body>          Send a plugin message",
-        "  /messages                                    Show all messages in content",
-        "  /events                                      Show all node events in content",
-        "  /console                                     Show all output in content",
-        "  /help                                        Show all commands in content",
-        "  /quit                                        Quit the TUI",
-        "",
-        "Navigation:  PgUp/PgDn scroll content  |  ↑↓ prompt history  |  Esc quit",
-    ]
-    .iter()
-    .map(|s| s.to_string())
-    .collect();
+    let mut lines = vec!["Available commands:".to_string()];
+    lines.extend(COMMANDS.iter().map(|(usage, summary)| format!("  {:<45}{}", usage, summary)));
+    lines.push(String::new());
+    lines.extend(
+        [
+            "Navigation:  PgUp/PgDn or mouse wheel scroll content  |  Ctrl+Home/End top/bottom  |  ↑↓ prompt history  |  ←→ Home/End Ctrl+A/E move in prompt  |  Alt+Enter new line  |  Ctrl+U/W delete to start/word  |  Tab complete command  |  Ctrl+P command palette  |  Ctrl+R re-run view  |  Ctrl+L dismiss error line  |  Alt+1–9 switch tab  |  F1–F10 quick-bar  |  ? all keys  |  Esc quit",
+            "Locked mode (--lock, /set lock on): Esc and Ctrl+C are ignored; /quit or Ctrl+Alt+Q exits.",
+            "Dry run: append --dry-run to /connection, /acceptConnection, /declineConnection, /cancelConnection, /message, /messagePlugin, /resend or /reply.",
+            "Auto-accept (/set autoAccept off|contacts|verified|all): which connection requests need no /acceptConnection.",
+        ]
+        .iter()
+        .map(|s| s.to_string()),
+    );
+    let aliases: Vec<String> = ALIASES.iter().map(|(alias, name)| format!("{} = {}", alias, name)).collect();
+    lines.push(format!("Command names ignore case. Aliases: {}.", aliases.join(", ")));
 
     app.push_event("[CMD] /help");
     app.set_content("Help", lines);
 }
 
+/// Every colour and icon the views use, with its meaning. Built from the
+/// theme and the same helpers the views call, so it cannot drift from them.
+fn cmd_legend(app: &mut App) {
+    let mut lines = vec![format!("Colours ({} theme):", app.startup.theme_name)];
+    lines.extend(
+        app.theme
+            .swatches()
+            .iter()
+            .map(|(name, _, meaning)| format!("{}{:<10} {}", SWATCH, name, meaning)),
+    );
+    lines.push(String::new());
+    lines.push("Icons:".to_string());
+    let icons = [
+        (lock_icon(true), "Connection established — end-to-end encrypted"),
+        (lock_icon(false), "Connection pending — not encrypted yet"),
+        (Direction::Outbound.label(), "You sent the connection request"),
+        (Direction::Inbound.label(), "The other side sent the connection request"),
+        (verified_mark(KeyStatus::Verified), "Key verified out of band (/verify)"),
+        (verified_mark(KeyStatus::Changed), "Key differs from the one on record (/acceptKey)"),
+        (SENT_MARK, "Message you sent"),
+        (Delivery::Pending.icon(), "Message stored by the node, awaiting confirmation"),
+        (Delivery::Delivered.icon(), "Message confirmed delivered by the peer"),
+        (Delivery::Unconfirmed.icon(), "No delivery confirmation yet (/resend)"),
+        (Delivery::Received.icon(), "Message received from a peer"),
+        (FOLD_CLOSED, "Folded section — click, Enter or /fold expand to open"),
+        (FOLD_OPEN, "Open section — click, Enter or /fold collapse to close"),
+    ];
+    lines.extend(icons.iter().map(|(icon, meaning)| format!("  {:<18}{}", icon.trim(), meaning)));
+    lines.push(String::new());
+    lines.push("Header:".to_string());
+    lines.push(format!("  {:<18}Node running with established connections", format!("{} green", HEADER_DOT)));
+    lines.push(format!("  {:<18}Node running but isolated: no established connection", format!("{} yellow", HEADER_DOT)));
+    lines.push(format!("  {:<18}Node stopped", format!("{} red", HEADER_DOT)));
+    lines.push(format!(
+        "  {:<18}Known peers; established {} and pending {} connections",
+        "peers N  conn E P", lock_icon(true), lock_icon(false)
+    ));
+    lines.push(format!("  {:<18}Sends awaiting a node reply; ⚠ from {} on", "queue N", SEND_QUEUE_WARN));
+    lines.push(format!("  {:<18}Messages sent over the last minute (/set sparkline)", "msgs N ▁▃▅█"));
+
+    app.push_event("[CMD] /legend");
+    app.set_content("Legend", lines);
+}
+
+/// `COMMANDS` entries matching a palette filter, best first: the filter's
+/// characters must appear in order in the usage, or it must occur in the
+/// summary. Matches at the start of the command name rank highest.
+pub fn palette_matches(filter: &str) -> Vec<(&'static str, &'static str)> {
+    let filter = filter.trim().trim_start_matches('/').to_lowercase();
+    let mut ranked: Vec<(u8, (&str, &str))> = COMMANDS
+        .iter()
+        .filter_map(|&(usage, summary)| {
+            let name = usage.to_lowercase();
+            let rank = if name[1..].starts_with(&filter) {
+                0
+            } else if is_subsequence(&filter, &name) {
+                1
+            } else if summary.to_lowercase().contains(&filter) {
+                2
+            } else {
+                return None;
+            };
+            Some((rank, (usage, summary)))
+        })
+        .collect();
+    ranked.sort_by_key(|(rank, _)| *rank);
+    ranked.into_iter().map(|(_, entry)| entry).collect()
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut chars = haystack.chars();
+    needle.chars().all(|c| chars.any(|h| h == c))
+}
+
+/// What picking `usage` in the palette does: the command to run, and whether
+/// it still needs arguments (then it goes to the prompt instead).
+pub fn palette_command(usage: &str) -> (String, bool) {
+    let mut words = usage.split(' ').peekable();
+    let mut fixed = Vec::new();
+    while let Some(word) = words.next_if(|w| !w.contains(['<', '[', '|'])) {
+        fixed.push(word);
+    }
+    let needs_args = words.any(|w| !w.starts_with('['));
+    (fixed.join(" "), needs_args)
+}
+
+/// Every command name (`/peers`, `/user`, …) once, in `/help` order.
+pub fn command_names() -> Vec<&'static str> {
+    let mut names: Vec<&str> = Vec::new();
+    for (usage, _) in COMMANDS {
+        let name = usage.split(' ').next().unwrap_or(usage);
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
 // ---------------------------------------------------------------------------
 // Quit
 // ---------------------------------------------------------------------------
 
 fn cmd_quit(app: &mut App) {
-    app.push_event("[APP] Quit requested.");
-    app.should_quit = true;
+    if confirm_quit(app, "/quit") {
+        app.push_event("[APP] Quit requested.");
+        app.should_quit = true;
+    }
+}
+
+/// Whether a quit attempt (`how`: "/quit", "Esc", …) should go ahead. With
+/// the node running the first attempt only asks for confirmation; the node is
+/// then shut down cleanly on the way out.
+pub fn confirm_quit(app: &mut App, how: &str) -> bool {
+    if app.node_tx.is_none() || app.quit_armed {
+        return true;
+    }
+    app.quit_armed = true;
+    show_lines(app, "Quit", vec![
+        format!("Node is running — press {} again (or /quit) to confirm.", how),
+        "It will be shut down cleanly first. Typing or running another command cancels.".to_string(),
+    ]);
+    false
 }
 
 // ---------------------------------------------------------------------------
 // Events / Console views
 // ---------------------------------------------------------------------------
 
-fn cmd_events(app: &mut App) {
-    let lines = app.events.clone();
+fn cmd_events(app: &mut App, rest: &str) {
+    let desc = match rest.trim() {
+        "" => app.events_desc,
+        "--desc" => true,
+        "--asc" => false,
+        other => {
+            app.set_error("Events", vec![format!("Unknown option '{}'. Usage: /events [--desc|--asc]", other)]);
+            return;
+        }
+    };
+    // Logged first, so the view built from the log shows it exactly once.
     app.push_event("[CMD] /events — showing events.");
-    let lines_with_fresh = {
-        let mut v = app.events.clone();
-        v.push("[CMD] /events — showing events.".to_string());
-        v
+    let mut lines: Vec<String> = app.events.iter().cloned().collect();
+    let mut times: Vec<Option<SystemTime>> = app.event_times.iter().copied().map(Some).collect();
+    if desc {
+        lines.reverse();
+        times.reverse();
+    }
+    let folds = fold_runs(&lines, desc);
+    app.set_content("Events", lines);
+    app.content_folds = folds;
+    app.content_times = times;
+    // auto-scroll to the newest end
+    app.content_scroll = if desc { app.content_top() } else { app.content_end() };
+}
+
+/// Runs of at least this many same-tag events are folded in `/events`.
+const FOLD_MIN_RUN: usize = 10;
+
+/// Fold long runs of consecutive lines sharing a `[TAG]`. The newest run —
+/// the last one, or the first when `newest_first` — is left open so the
+/// latest events stay visible.
+fn fold_runs(lines: &[String], newest_first: bool) -> Vec<Fold> {
+    let mut folds = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let tag = line_tag(&lines[start]);
+        let len = lines[start..].iter().take_while(|l| line_tag(l) == tag).count();
+        if let Some(tag) = tag
+            && len >= FOLD_MIN_RUN
+        {
+            folds.push(Fold { start, len, label: tag.to_string(), unit: "events", open: false });
+        }
+        start += len;
+    }
+    if newest_first {
+        if let Some(first) = folds.first_mut()
+            && first.start == 0
+        {
+            first.open = true;
+        }
+    } else if let Some(last) = folds.last_mut()
+        && last.start + last.len == lines.len()
+    {
+        last.open = true;
+    }
+    folds
+}
+
+/// The leading `[TAG]` of a log line, if any.
+fn line_tag(line: &str) -> Option<&str> {
+    if !line.starts_with('[') {
+        return None;
+    }
+    line.find(']').map(|end| &line[..=end])
+}
+
+fn cmd_watch(app: &mut App, rest: &str) {
+    let nick = rest.trim();
+    if nick.is_empty() {
+        show_lines(app, "Watch", vec!["Usage: /watch <nick>".to_string()]);
+        return;
+    }
+
+    let watch = Watch { nick: nick.to_string(), id: resolve_nick(app, nick) };
+    let (mut matching, mut times): (Vec<String>, Vec<Option<SystemTime>>) = app
+        .events
+        .iter()
+        .zip(&app.event_times)
+        .filter(|(l, _)| watch.matches(l))
+        .map(|(l, at)| (l.clone(), Some(*at)))
+        .unzip();
+    if app.events_desc {
+        matching.reverse();
+        times.reverse();
+    }
+    let note = match &watch.id {
+        Some(id) => format!("[CMD] /watch — following {} ({}).", nick, truncate_id(id, 16)),
+        None => format!("[CMD] /watch — following '{}' (no known user; matching by name only).", nick),
+    };
+
+    app.watch = Some(watch);
+    app.set_content(format!("Watch: {}", nick), matching);
+    app.content_times = times;
+    // Pushed after the view is up so the note shows in it too.
+    app.push_event(note);
+    app.content_scroll = if app.events_desc { app.content_top() } else { app.content_end() };
+}
+
+fn cmd_unwatch(app: &mut App) {
+    match app.watch.take() {
+        Some(watch) => {
+            app.push_event(format!("[CMD] /unwatch — stopped following {}.", watch.nick));
+            show_lines(app, "Watch", vec![format!("Stopped following {}.", watch.nick)]);
+        }
+        None => show_lines(app, "Watch", vec!["Not watching anyone.".to_string()]),
+    }
+}
+
+fn cmd_fold(app: &mut App, rest: &str) {
+    let open = match rest.trim() {
+        "expand" => true,
+        "collapse" => false,
+        _ => {
+            app.set_error("Fold", vec!["Usage: /fold expand|collapse".to_string()]);
+            return;
+        }
+    };
+    if app.content_folds.is_empty() {
+        app.push_error("Nothing folded in this view.");
+        return;
+    }
+    for fold in &mut app.content_folds {
+        fold.open = open;
+    }
+}
+
+/// Search the current view, case-insensitively, and jump to the first match.
+/// Without text, or with the same text again, go to the next match.
+fn cmd_find(app: &mut App, rest: &str) {
+    let term = rest.trim();
+    if term.is_empty() || app.search.as_ref().is_some_and(|s| s.term.eq_ignore_ascii_case(term)) {
+        find_next(app);
+        return;
+    }
+
+    let needle = term.to_lowercase();
+    let matches: Vec<usize> = app
+        .content_lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.to_lowercase().contains(&needle))
+        .map(|(i, _)| i)
+        .collect();
+    if matches.is_empty() {
+        app.search = None;
+        app.push_error(format!("'{}' not found in this view.", term));
+        return;
+    }
+    app.push_output(format!("'{}': {} matching line(s). Ctrl+N for the next.", term, matches.len()));
+    app.search = Some(Search { term: term.to_string(), matches, current: 0 });
+    jump_to_match(app);
+}
+
+/// Move to the next match of the current search, wrapping at the end.
+pub fn find_next(app: &mut App) {
+    let Some(search) = app.search.as_mut() else {
+        app.push_error("Nothing to find. Use /find <text>.");
+        return;
+    };
+    search.current = (search.current + 1) % search.matches.len();
+    jump_to_match(app);
+}
+
+/// Scroll the current match to the middle of the view, opening the fold
+/// that hides it.
+fn jump_to_match(app: &mut App) {
+    let Some(line) = app.search.as_ref().map(|s| s.matches[s.current]) else {
+        return;
+    };
+    if let Some(fold) = app.content_folds.iter_mut().find(|f| (f.start..f.start + f.len).contains(&line)) {
+        fold.open = true;
+    }
+    let row = app
+        .display_lines()
+        .iter()
+        .position(|row| matches!(row, DisplayLine::Text(i, _) if *i == line))
+        .unwrap_or(0);
+    app.content_scroll = row.saturating_sub(app.content_geometry.visible / 2) as u16;
+}
+
+/// Write the current view, or the whole events or output log, to a file.
+fn cmd_save(app: &mut App, rest: &str) {
+    let (what, path) = match split_command(rest.trim()) {
+        ("events", path) if !path.is_empty() => ("events", path),
+        ("output", path) if !path.is_empty() => ("output", path),
+        _ => ("view", rest.trim()),
+    };
+    if path.is_empty() {
+        show_lines(app, "Save", vec!["Usage: /save [events|output] <path>".to_string()]);
+        return;
+    }
+
+    // The logs are saved with the local time of each line, as the views show
+    // them with `/set timestamps absolute`.
+    let stamped = |log: &VecDeque<String>, times: &VecDeque<SystemTime>| -> Vec<String> {
+        log.iter()
+            .zip(times)
+            .map(|(line, &at)| format!("{}  {}", TimeDisplay::Absolute.label(at).unwrap_or_default(), line))
+            .collect()
+    };
+    let lines: Vec<String> = match what {
+        "events" => stamped(&app.events, &app.event_times),
+        "output" => stamped(&app.output, &app.output_times),
+        _ => app.content_lines.clone(),
+    };
+    let path = expand_home(path);
+    let mut text = lines.join("\n");
+    text.push('\n');
+    let count = lines.len();
+
+    let result = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() && !parent.is_dir() => {
+            Err(anyhow!("directory {} does not exist", parent.display()))
+        }
+        _ => std::fs::write(&path, text).map_err(anyhow::Error::from),
+    };
+    match result {
+        Ok(()) => {
+            app.push_output(format!("Saved {} line(s) of {} to {}.", count, what, path.display()));
+            show_lines(app, "Save", vec![format!("Saved {} line(s) of {} to {}.", count, what, path.display())]);
+        }
+        Err(e) => app.set_error("Save", vec![format!("Could not write {}: {e}", path.display())]),
+    }
+}
+
+/// One message in a `/export messages` file.
+#[derive(Debug, Serialize)]
+struct ExportedMessage<'a> {
+    direction: &'static str,
+    peer_id: &'a str,
+    peer_nick: &'a str,
+    from_id: &'a str,
+    to_id: &'a str,
+    /// Unix seconds when it was sent or received.
+    timestamp: u64,
+    plugin_type: &'a str,
+    body: &'a serde_json::Value,
+    hash: &'a str,
+    delivery: &'static str,
+}
+
+impl<'a> From<&'a MessageEntry> for ExportedMessage<'a> {
+    fn from(m: &'a MessageEntry) -> Self {
+        Self {
+            direction: if m.delivery == Delivery::Received { "received" } else { "sent" },
+            peer_id: m.peer_id(),
+            peer_nick: &m.nick,
+            from_id: &m.from_id,
+            to_id: &m.to_id,
+            timestamp: m.at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            plugin_type: &m.plugin_type,
+            body: &m.body,
+            hash: &m.hash,
+            delivery: match m.delivery {
+                Delivery::Pending => "pending",
+                Delivery::Delivered => "delivered",
+                Delivery::Unconfirmed => "unconfirmed",
+                Delivery::Received => "received",
+            },
+        }
+    }
+}
+
+/// Write the message history, machine-readable, for backup or moving to
+/// another device: a JSON array, or one object per line for a `.jsonl` path.
+fn cmd_export(app: &mut App, rest: &str) {
+    let path = match split_command(rest.trim()) {
+        ("messages", path) if !path.is_empty() => path,
+        _ => {
+            show_lines(app, "Export", vec!["Usage: /export messages <path>  (.jsonl for one message per line)".to_string()]);
+            return;
+        }
+    };
+    let path = expand_home(path);
+    let entries: Vec<ExportedMessage> = app.messages.iter().map(ExportedMessage::from).collect();
+    let jsonl = path.extension().is_some_and(|ext| ext == "jsonl");
+    let text = if jsonl {
+        entries
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<serde_json::Result<Vec<_>>>()
+            .map(|lines| lines.iter().map(|l| l.clone() + "\n").collect())
+    } else {
+        serde_json::to_string_pretty(&entries).map(|json| format!("{}\n", json))
+    };
+    let count = entries.len();
+
+    let result = match (path.parent(), text) {
+        (_, Err(e)) => Err(anyhow::Error::from(e)),
+        (Some(parent), _) if !parent.as_os_str().is_empty() && !parent.is_dir() => {
+            Err(anyhow!("directory {} does not exist", parent.display()))
+        }
+        (_, Ok(text)) => std::fs::write(&path, text).map_err(anyhow::Error::from),
+    };
+    match result {
+        Ok(()) => {
+            let msg = format!(
+                "Exported {} message(s) to {} as {}.",
+                count,
+                path.display(),
+                if jsonl { "JSON lines" } else { "JSON" }
+            );
+            app.push_output(msg.clone());
+            show_lines(app, "Export", vec![msg]);
+        }
+        Err(e) => app.set_error("Export", vec![format!("Could not write {}: {e}", path.display())]),
+    }
+}
+
+/// `path` with a leading `~` replaced by the home directory.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Largest OSC 52 payload sent, base64-encoded; terminals drop or truncate
+/// longer sequences.
+const OSC52_MAX: usize = 100_000;
+
+/// Copy the current view, or one line of it, to the clipboard: the system
+/// one by default, or through the terminal with `/set clipboard osc52`.
+fn cmd_copy(app: &mut App, rest: &str) {
+    let arg = rest.trim();
+    let text = if arg.is_empty() {
+        app.content_lines.join("\n")
+    } else {
+        match arg.parse::<usize>() {
+            Ok(n) if (1..=app.content_lines.len()).contains(&n) => app.content_lines[n - 1].trim().to_string(),
+            _ => {
+                app.set_error("Copy", vec![format!(
+                    "Usage: /copy [line], with line from 1 to {} in this view.", app.content_lines.len()
+                )]);
+                return;
+            }
+        }
+    };
+    if text.is_empty() {
+        app.push_output("Nothing to copy.");
+        return;
+    }
+
+    let chars = text.chars().count();
+    match app.clipboard_mode {
+        ClipboardMode::System => {
+            let result = app.clipboard.take().map_or_else(arboard::Clipboard::new, Ok).and_then(|mut clipboard| {
+                let result = clipboard.set_text(text);
+                app.clipboard = Some(clipboard);
+                result
+            });
+            match result {
+                Ok(()) => app.push_output(format!("Copied {} characters to the clipboard.", chars)),
+                Err(e) => app.set_error("Copy", vec![
+                    format!("Could not use the system clipboard: {e}"),
+                    "Over SSH or without a display, try /set clipboard osc52.".to_string(),
+                ]),
+            }
+        }
+        ClipboardMode::Osc52 => {
+            let encoded = BASE64.encode(text.as_bytes());
+            if encoded.len() > OSC52_MAX {
+                app.set_error("Copy", vec![format!(
+                    "Too much to copy at once ({} bytes). Copy a single line, or use /save instead.", text.len()
+                )]);
+                return;
+            }
+            let mut out = std::io::stdout();
+            let result = out.write_all(format!("\x1b]52;c;{}\x07", encoded).as_bytes()).and_then(|_| out.flush());
+            match result {
+                // The terminal does not answer, so whether it honoured the
+                // escape is unknown.
+                Ok(()) => app.push_output(format!(
+                    "Sent {} characters to the terminal clipboard (OSC 52); some terminals ignore it.",
+                    chars
+                )),
+                Err(e) => app.set_error("Copy", vec![format!("Could not reach the terminal: {e}")]),
+            }
+        }
+    }
+}
+
+async fn cmd_ids(app: &mut App, rest: &str) -> Result<()> {
+    app.show_full_ids = match rest.trim() {
+        "full" => true,
+        "short" => false,
+        "" => {
+            let mode = if app.show_full_ids { "full" } else { "short" };
+            show_lines(app, "Ids", vec![format!("Ids are shown {}. Usage: /ids full|short", mode)]);
+            return Ok(());
+        }
+        other => {
+            app.set_error("Ids", vec![format!("Unknown option '{}'. Usage: /ids full|short", other)]);
+            return Ok(());
+        }
+    };
+    app.push_event(format!("[SET] ids = {}", rest.trim()));
+    app.push_output(format!("Ids are now shown {}.", rest.trim()));
+    // Redraw the current view in the new form, where re-running it is safe.
+    if app.content_command.as_deref().is_some_and(is_view_command) {
+        cmd_refresh(app).await?;
+    }
+    Ok(())
+}
+
+/// Empty the content pane and its scrollback. The events, output and message
+/// logs are untouched.
+fn cmd_clear(app: &mut App) {
+    app.archive_next = false;
+    app.scrollback_lines.clear();
+    app.set_content("Accord", Vec::new());
+}
+
+/// List the kept views, or bring one back with its scroll position and folds
+/// as it was left.
+fn cmd_tab(app: &mut App, rest: &str) {
+    let arg = rest.trim();
+    if arg.is_empty() {
+        let current = app.content_title.trim().to_string();
+        let mut lines = vec![format!("Tabs  ({})", app.tab_order.len()), String::new()];
+        if app.tab_order.is_empty() {
+            lines.push("  No views kept yet. Views like /events or /users become tabs once shown.".to_string());
+        }
+        for (i, title) in app.tab_order.iter().enumerate() {
+            let command = if *title == current {
+                app.content_command.as_deref().unwrap_or_default()
+            } else {
+                app.tabs.get(title).map_or("", |tab| tab.command.as_str())
+            };
+            let mark = if *title == current { "  ← showing" } else { "" };
+            lines.push(format!("  {:>2}  {:<24}  {}{}", i + 1, title, command, mark));
+        }
+        lines.push(String::new());
+        lines.push("Switch with /tab <n|name>, or Alt+1–9.".to_string());
+        app.set_content("Tabs", lines);
+        return;
+    }
+
+    let title = match arg.parse::<usize>() {
+        Ok(n) => n.checked_sub(1).and_then(|i| app.tab_order.get(i)).cloned(),
+        Err(_) => app.tab_order.iter().find(|t| t.eq_ignore_ascii_case(arg)).cloned(),
+    };
+    let Some(title) = title else {
+        app.set_error("Tab", vec![format!("No tab '{}'. Type /tab for the list.", arg)]);
+        return;
+    };
+    if title == app.content_title.trim() {
+        return;
+    }
+    let Some(tab) = app.tabs.get(&title).cloned() else {
+        app.tab_order.retain(|t| *t != title);
+        app.set_error("Tab", vec![format!("Nothing is kept for the {} view any more.", title)]);
+        return;
     };
-    app.set_content("Events", lines_with_fresh);
-    // auto-scroll to bottom
-    app.content_scroll = lines.len() as u16;
+    app.set_content(title.as_str(), tab.lines);
+    app.content_times = tab.times;
+    app.content_folds = tab.folds;
+    app.content_scroll = tab.scroll;
+    app.content_command = Some(tab.command);
+}
+
+/// Keep earlier command outputs above the current one, to page back to with
+/// PgUp, or show only the latest.
+fn cmd_scrollback(app: &mut App, rest: &str) {
+    let arg = rest.trim();
+    if arg.is_empty() {
+        show_lines(app, "Scrollback", vec![format!("Scrollback is {}. Usage: /scrollback on|off", on_off(app.scrollback))]);
+        return;
+    }
+    let Some(on) = parse_switch(arg) else {
+        app.set_error("Scrollback", vec![format!("Unknown value '{}'. Usage: /scrollback on|off", arg)]);
+        return;
+    };
+    app.scrollback = on;
+    if !on {
+        app.scrollback_lines.clear();
+        app.content_scroll = 0;
+    }
+    app.push_event(format!("[SET] scrollback = {}", on_off(on)));
+    app.push_output(if on {
+        "Command outputs now pile up; PgUp shows earlier ones.".to_string()
+    } else {
+        "Only the latest command output is shown.".to_string()
+    });
+}
+
+/// Switch between the content alone and the content beside a live events
+/// pane.
+fn cmd_layout(app: &mut App, rest: &str) {
+    app.split_view = match rest.trim() {
+        "split" => true,
+        "single" => false,
+        "" => {
+            let layout = if app.split_view { "split" } else { "single" };
+            show_lines(app, "Layout", vec![format!("Layout is {}. Usage: /layout split|single", layout)]);
+            return;
+        }
+        other => {
+            app.set_error("Layout", vec![format!("Unknown layout '{}'. Usage: /layout split|single", other)]);
+            return;
+        }
+    };
+    app.push_event(format!("[SET] layout = {}", rest.trim()));
+    app.push_output(if app.split_view {
+        "Live events are shown beside the content (on terminals at least 80 columns wide).".to_string()
+    } else {
+        "The content has the full width again.".to_string()
+    });
 }
 
 fn cmd_console(app: &mut App) {
     app.push_output("[CMD] /console — showing output log.");
-    let lines = app.output.clone();
+    let lines = app.output.iter().cloned().collect();
     app.set_content("Console", lines);
-    app.content_scroll = app.output.len() as u16;
+    app.content_times = app.output_times.iter().copied().map(Some).collect();
+    app.content_scroll = app.content_end();
+}
+
+fn cmd_history(app: &mut App, rest: &str) {
+    let arg = rest.trim();
+    if arg.is_empty() {
+        let mut lines = vec![format!("Prompt history  ({})", app.prompt_history.len()), String::new()];
+        if app.prompt_history.is_empty() {
+            lines.push("  Nothing run yet.".to_string());
+        }
+        for (i, entry) in app.prompt_history.iter().enumerate() {
+            lines.push(format!("  {:>4}  {}", i + 1, entry.replace('\n', " ⏎ ")));
+        }
+        lines.push(String::new());
+        lines.push("Use /history <n> to edit and re-run an entry.".to_string());
+        app.set_content("History", lines);
+        app.content_scroll = app.content_end();
+        return;
+    }
+
+    let len = app.prompt_history.len();
+    match arg.parse::<usize>() {
+        Ok(n) if (1..=len).contains(&n) => {
+            let entry = app.prompt_history[n - 1].clone();
+            app.set_prompt(entry);
+            app.prompt_history_idx = None;
+        }
+        _ => app.set_error("History", vec![format!("Usage: /history [n]  — n between 1 and {}.", len)]),
+    }
 }
 
-fn cmd_messages(app: &mut App) {
+fn cmd_messages(app: &mut App, rest: &str) {
+    let nick = rest.trim();
+    app.messages_peer = None;
+    if !nick.is_empty() {
+        let Some(id) = resolve_nick(app, nick) else {
+            show_lines(app, "Messages", vec![format!("No user found with nick '{}'. Use /users to see known users.", nick)]);
+            return;
+        };
+        app.messages_peer = Some((nick.to_string(), id));
+    }
     app.push_event("[CMD] /messages — showing messages.");
-    let mut lines = vec![format!("Messages  ({})", app.messages.len()), String::new()];
-    if app.messages.is_empty() {
-        lines.push("  No messages yet. Use /message <nick> <body> to send one.".to_string());
+    show_messages(app);
+}
+
+/// Render `app.messages`, narrowed to `app.messages_peer` if set, into the
+/// content area.
+fn show_messages(app: &mut App) {
+    let peer = app.messages_peer.as_ref().map(|(_, id)| id.as_str());
+    let shown: Vec<&MessageEntry> =
+        app.messages.iter().filter(|m| peer.is_none_or(|id| m.peer_id() == id)).collect();
+    let mut lines = match &app.messages_peer {
+        Some((nick, _)) => vec![format!("Messages with {}  ({})", nick, shown.len()), String::new()],
+        None => vec![format!("Messages  ({})", shown.len()), String::new()],
+    };
+    let mut times = vec![None; lines.len()];
+    if shown.is_empty() {
+        match &app.messages_peer {
+            Some((nick, _)) => lines.push(format!("  No messages with {} yet. Use /message {} <body> to send one.", nick, nick)),
+            None => lines.push("  No messages yet. Use /message <nick> <body> to send one.".to_string()),
+        }
     } else {
-        lines.extend(app.messages.clone());
+        for m in &shown {
+            lines.push(message_line(app, m));
+            times.push(Some(m.at));
+            if let Some(quote) = reply_quote(app, m) {
+                lines.push(quote);
+                times.push(None);
+            }
+        }
+        if shown.iter().any(|m| m.delivery == Delivery::Unconfirmed) {
+            lines.push(String::new());
+            lines.push("  ⚠ = no delivery confirmation in time. Use /resend <hash> to try again.".to_string());
+        }
+        if shown.iter().any(|m| m.skew.is_some()) {
+            lines.push(String::new());
+            lines.push("  ⚠ clock skew = the sender's clock is far off; messages are listed in arrival order.".to_string());
+        }
     }
     app.set_content("Messages", lines);
+    app.content_times = times;
+}
+
+fn message_line(app: &App, m: &MessageEntry) -> String {
+    format!(
+        "{} [{}→{}]  [{}]  {}  ({}){}",
+        m.marker(),
+        id_label(app, &m.from_id, 8),
+        id_label(app, &m.to_id, 8),
+        m.plugin_type,
+        m.body,
+        id_label(app, &m.hash, 12),
+        if m.skew.is_some() { "  ⚠ clock skew" } else { "" }
+    )
+}
+
+/// Quote line shown under a reply, referencing the message it answers.
+fn reply_quote(app: &App, m: &MessageEntry) -> Option<String> {
+    let target = m.body.get("in_reply_to")?.as_str()?;
+    let quoted = match app.messages.iter().find(|o| o.hash == target) {
+        Some(original) => format!("\"{}\"", truncate_text(&message_text(&original.body).replace('\n', " "), 48)),
+        None => "(not available locally)".to_string(),
+    };
+    Some(format!("      ↪ re {}: {}", id_label(app, target, 12), quoted))
+}
+
+/// A message body for the Message view, pretty-printed over as many lines as
+/// it takes, under a `body :` label.
+fn body_lines(body: &serde_json::Value) -> Vec<String> {
+    let pretty = serde_json::to_string_pretty(body).unwrap_or_else(|_| body.to_string());
+    pretty
+        .lines()
+        .enumerate()
+        .map(|(i, line)| if i == 0 { format!("  body       : {}", line) } else { format!("{:15}{}", "", line) })
+        .collect()
+}
+
+/// The human-readable text of a message body: its `text` field for text
+/// messages, the raw JSON otherwise.
+fn message_text(body: &serde_json::Value) -> String {
+    match body.get("text").and_then(|t| t.as_str()) {
+        Some(text) => printable(text).into_owned(),
+        None => body.to_string(),
+    }
+}
+
+/// Add our wall-clock send time (`sent_at`, Unix seconds) to a text message,
+/// replacing any earlier stamp on a resend. Receivers compare it with their
+/// own clock to spot skew; the message format itself has no timestamp.
+fn stamp_sent_at(plugin_type: &str, mut body: serde_json::Value) -> serde_json::Value {
+    if plugin_type == "text"
+        && let Some(fields) = body.as_object_mut()
+        && let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH)
+    {
+        fields.insert("sent_at".to_string(), now.as_secs().into());
+    }
+    body
+}
+
+/// Sender clocks further than this from ours, in seconds, count as skewed.
+const SKEW_LIMIT: i64 = 5 * 60;
+
+/// Record a message a peer sent us, and show it if the messages view is up.
+pub fn receive_message(app: &mut App, mut message: Message) {
+    let hash: String = serde_json::to_vec(&message)
+        .map(|bytes| Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect())
+        .unwrap_or_default();
+    // Whatever a peer controls is cleaned before it reaches the logs.
+    let nick = load_known_user(&message.from, app.storage())
+        .ok()
+        .and_then(|meta| meta.display_name)
+        .map(|name| printable(&name).into_owned())
+        .unwrap_or_else(|| truncate_id(&printable(&message.from), 16));
+    message.plugin_type = printable(&message.plugin_type).into_owned();
+
+    // Pings, pongs and receipts are answered and settled here, not kept as
+    // messages.
+    match message.plugin_type.as_str() {
+        "ping" => return answer_ping(app, &message, &nick),
+        "pong" if settle_ping(app, &message, &nick) => return,
+        "receipt" if settle_receipt(app, &message, &nick) => return,
+        _ => {}
+    }
+    // Confirm delivery to the sender, whose message waits for this.
+    let receipt_body = serde_json::json!({ "hash": hash });
+    let receipt = Message::new(message.to.clone(), message.from.as_str(), "receipt", receipt_body);
+    store_in_background(app, &receipt);
+
+    let skew = clock_skew(&message.plugin_body);
+    if let Some(secs) = skew {
+        app.clock_skew.insert(message.from.clone(), secs);
+        app.push_event(format!("[MSG] ⚠ {}'s clock is {} — listing by arrival time.", nick, skew_label(secs)));
+    }
+    app.activity.record();
+    if let Err(e) = app.message_counts.record(&message.from) {
+        app.push_event(format!("[MSG] Message count not saved: {e}"));
+    }
+    app.push_event(format!("[MSG] ← {} [{}] (hash: {})", nick, message.plugin_type, truncate_id(&hash, 12)));
+    app.push_message(MessageEntry {
+        from_id: message.from,
+        to_id: message.to,
+        nick,
+        plugin_type: message.plugin_type,
+        body: message.plugin_body,
+        hash,
+        sent_at: Instant::now(),
+        at: SystemTime::now(),
+        delivery: Delivery::Received,
+        skew,
+    });
+    if app.content_title.trim() == "Messages" {
+        show_messages(app);
+        app.content_scroll = app.content_end();
+    }
+}
+
+/// Mark the sent message a receipt confirms as delivered. Returns whether
+/// it confirmed one of ours.
+fn settle_receipt(app: &mut App, receipt: &Message, nick: &str) -> bool {
+    let Some(hash) = receipt.plugin_body.get("hash").and_then(|h| h.as_str()) else {
+        return false;
+    };
+    let Some(m) = app.messages.iter_mut().find(|m| {
+        m.hash == hash && m.to_id == receipt.from && matches!(m.delivery, Delivery::Pending | Delivery::Unconfirmed)
+    }) else {
+        return false;
+    };
+    m.delivery = Delivery::Delivered;
+    app.push_event(format!("[MSG] ✓ {} received {}.", nick, truncate_id(hash, 12)));
+    if app.content_title.trim() == "Messages" {
+        let scroll = app.content_scroll;
+        show_messages(app);
+        app.content_scroll = scroll;
+    }
+    true
+}
+
+/// How far the `sent_at` stamp of a message body is from our clock, if
+/// further than `SKEW_LIMIT`.
+fn clock_skew(body: &serde_json::Value) -> Option<i64> {
+    let sent_at = body.get("sent_at")?.as_i64()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+    let skew = sent_at - now;
+    (skew.abs() > SKEW_LIMIT).then_some(skew)
+}
+
+/// "12m ahead", "3h 5m behind", …
+fn skew_label(secs: i64) -> String {
+    let mins = secs.unsigned_abs() / 60;
+    let amount = if mins >= 60 { format!("{}h {}m", mins / 60, mins % 60) } else { format!("{}m", mins) };
+    format!("{} {}", amount, if secs > 0 { "ahead" } else { "behind" })
+}
+
+/// Flag sent messages whose confirmation did not arrive within the ack
+/// timeout. Called on every tick of the main loop.
+pub fn check_pending_acks(app: &mut App) {
+    let timeout = app.ack_timeout;
+    let mut expired = Vec::new();
+    for m in app.messages.iter_mut() {
+        if m.delivery == Delivery::Pending && m.sent_at.elapsed() >= timeout {
+            m.delivery = Delivery::Unconfirmed;
+            expired.push((m.nick.clone(), truncate_id(&m.hash, 12)));
+        }
+    }
+    if expired.is_empty() {
+        return;
+    }
+    for (nick, hash) in expired {
+        app.push_event(format!(
+            "[MSG] ⚠ No delivery confirmation from {} after {}s — /resend {}",
+            nick,
+            timeout.as_secs(),
+            hash.trim_end_matches('…')
+        ));
+    }
+    if app.content_title.trim() == "Messages" {
+        let scroll = app.content_scroll;
+        show_messages(app);
+        app.content_scroll = scroll;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Settings
+// ---------------------------------------------------------------------------
+
+/// Bounds for `/set maxLogLines`; the upper one keeps a full log within the
+/// content view's `u16` scroll offset.
+const MIN_LOG_LINES: usize = 100;
+const MAX_LOG_LINES: usize = 50_000;
+
+fn cmd_set(app: &mut App, rest: &str) {
+    let (key, value) = split_command(rest.trim());
+    match key {
+        "" => {
+            let lines = vec![
+                "Settings".to_string(),
+                String::new(),
+                format!("  {:<12}{}", "prompt", app.prompt_prefix),
+                format!("  {:<12}{}", "autoSlash", on_off(app.auto_slash)),
+                format!("  {:<12}{}", "lock", on_off(app.locked)),
+                format!("  {:<12}{}s", "ackTimeout", app.ack_timeout.as_secs()),
+                format!("  {:<12}{}", "autoAccept", app.auto_accept.name()),
+                format!("  {:<12}{}", "sparkline", on_off(app.show_sparkline)),
+                format!("  {:<12}{}", "quickbar", on_off(app.show_quickbar)),
+                format!("  {:<12}{}", "timestamps", app.time_display.name()),
+                format!("  {:<12}{}", "clipboard", app.clipboard_mode.name()),
+                format!("  {:<12}{}", "eventsOrder", if app.events_desc { "desc" } else { "asc" }),
+                format!("  {:<12}{}", "maxLogLines", app.max_log_lines),
+                format!("  {:<12}{}", "portFallback", on_off(app.port_fallback)),
+                format!("  {:<12}{}ms", "tick", app.tick_interval.as_millis()),
+                String::new(),
+                "Usage: /set <setting> <value>".to_string(),
+            ];
+            app.set_content("Settings", lines);
+        }
+        "prompt" => {
+            app.prompt_prefix = if value.is_empty() {
+                DEFAULT_PROMPT_PREFIX.to_string()
+            } else {
+                value.to_string()
+            };
+            app.push_event(format!("[SET] prompt = {}", app.prompt_prefix));
+            show_lines(app, "Settings", vec![format!("Prompt prefix set to '{}'.", app.prompt_prefix)]);
+        }
+        "autoSlash" => match parse_switch(value) {
+            Some(on) => {
+                app.auto_slash = on;
+                app.push_event(format!("[SET] autoSlash = {}", on_off(on)));
+                let msg = if on {
+                    "Typing into an empty prompt starts a command: '/' is added for you."
+                } else {
+                    "The prompt takes input as typed; start commands with '/' yourself."
+                };
+                show_lines(app, "Settings", vec![msg.to_string()]);
+            }
+            None => app.set_error("Settings", vec!["Usage: /set autoSlash on|off".to_string()]),
+        },
+        "lock" => match parse_switch(value) {
+            Some(locked) => {
+                app.locked = locked;
+                app.push_event(format!("[SET] lock = {}", on_off(locked)));
+                let msg = if locked {
+                    "Locked: Esc and Ctrl+C no longer quit. Use /quit (operator: Ctrl+Alt+Q)."
+                } else {
+                    "Unlocked: Esc and Ctrl+C quit again."
+                };
+                show_lines(app, "Settings", vec![msg.to_string()]);
+            }
+            None => app.set_error("Settings", vec!["Usage: /set lock on|off".to_string()]),
+        },
+        "ackTimeout" => match value.trim_end_matches('s').parse::<u64>() {
+            Ok(secs) if secs > 0 => {
+                app.ack_timeout = Duration::from_secs(secs);
+                app.push_event(format!("[SET] ackTimeout = {}s", secs));
+                show_lines(app, "Settings", vec![format!("Messages are flagged after {}s without confirmation.", secs)]);
+            }
+            _ => app.set_error("Settings", vec!["Usage: /set ackTimeout <seconds>".to_string()]),
+        },
+        "sparkline" => match parse_switch(value) {
+            Some(on) => {
+                app.show_sparkline = on;
+                app.push_event(format!("[SET] sparkline = {}", on_off(on)));
+                let msg = if on {
+                    "The header shows message activity over the last minute."
+                } else {
+                    "Activity sparkline hidden."
+                };
+                show_lines(app, "Settings", vec![msg.to_string()]);
+            }
+            None => app.set_error("Settings", vec!["Usage: /set sparkline on|off".to_string()]),
+        },
+        "timestamps" => match TimeDisplay::parse(value) {
+            Some(display) => {
+                app.time_display = display;
+                app.push_event(format!("[SET] timestamps = {}", display.name()));
+                show_lines(app, "Settings", vec![format!("Event and message times are now shown {}.", match display {
+                    TimeDisplay::Relative => "relative (\"2m ago\")",
+                    TimeDisplay::Absolute => "as local time of day",
+                    TimeDisplay::Off => "nowhere",
+                })]);
+            }
+            None => app.set_error("Settings", vec!["Usage: /set timestamps relative|absolute|off".to_string()]),
+        },
+        "clipboard" => match ClipboardMode::parse(value) {
+            Some(mode) => {
+                app.clipboard_mode = mode;
+                app.push_event(format!("[SET] clipboard = {}", mode.name()));
+                show_lines(app, "Settings", vec![match mode {
+                    ClipboardMode::System => "/copy now uses the system clipboard.",
+                    ClipboardMode::Osc52 => "/copy now goes through the terminal (OSC 52), which works over SSH.",
+                }
+                .to_string()]);
+            }
+            None => app.set_error("Settings", vec!["Usage: /set clipboard system|osc52".to_string()]),
+        },
+        "eventsOrder" => match value {
+            "asc" | "desc" => {
+                app.events_desc = value == "desc";
+                app.push_event(format!("[SET] eventsOrder = {}", value));
+                let msg = if app.events_desc {
+                    "/events and /watch now show the newest events first."
+                } else {
+                    "/events and /watch now show the newest events last."
+                };
+                let mut lines = vec![msg.to_string()];
+                if let Err(e) = config::save_setting("events_order", value.into()) {
+                    app.push_event(format!("[SET] eventsOrder not saved: {e}"));
+                    lines.push(format!("  ⚠ Not saved ({e}); it applies to this session only."));
+                }
+                show_lines(app, "Settings", lines);
+            }
+            _ => app.set_error("Settings", vec!["Usage: /set eventsOrder asc|desc".to_string()]),
+        },
+        "maxLogLines" => match value.parse::<usize>() {
+            Ok(n) if (MIN_LOG_LINES..=MAX_LOG_LINES).contains(&n) => {
+                app.max_log_lines = n;
+                app.trim_logs();
+                app.push_event(format!("[SET] maxLogLines = {}", n));
+                show_lines(app, "Settings", vec![format!("Events, output and messages now keep the newest {} entries each.", n)]);
+            }
+            _ => app.set_error("Settings", vec![format!(
+                "Usage: /set maxLogLines <{}–{}>", MIN_LOG_LINES, MAX_LOG_LINES
+            )]),
+        },
+        "portFallback" => match parse_switch(value) {
+            Some(on) => {
+                app.port_fallback = on;
+                app.push_event(format!("[SET] portFallback = {}", on_off(on)));
+                let msg = if on {
+                    format!("If the listen port is taken, /startNode tries the next {} ports.", PORT_RETRIES)
+                } else {
+                    "/startNode only tries the configured listen port.".to_string()
+                };
+                show_lines(app, "Settings", vec![msg]);
+            }
+            None => app.set_error("Settings", vec!["Usage: /set portFallback on|off".to_string()]),
+        },
+        "tick" => match config::parse_tick(value) {
+            Some(tick) => {
+                app.tick_interval = tick;
+                app.push_event(format!("[SET] tick = {}ms", tick.as_millis()));
+                show_lines(app, "Settings", vec![format!(
+                    "Periodic checks now run every {}ms; the screen is only redrawn when something changes.",
+                    tick.as_millis()
+                )]);
+            }
+            None => app.set_error("Settings", vec![format!(
+                "Usage: /set tick <{}–{} ms>", config::MIN_TICK_MS, config::MAX_TICK_MS
+            )]),
+        },
+        "quickbar" => match parse_switch(value) {
+            Some(on) => {
+                app.show_quickbar = on;
+                app.push_event(format!("[SET] quickbar = {}", on_off(on)));
+                let msg = if on {
+                    "Quick-bar shown above the prompt."
+                } else {
+                    "Quick-bar hidden; F1–F10 still run their bound commands."
+                };
+                show_lines(app, "Settings", vec![msg.to_string()]);
+            }
+            None => app.set_error("Settings", vec!["Usage: /set quickbar on|off".to_string()]),
+        },
+        "autoAccept" => match AutoAccept::parse(value) {
+            Some(policy) => {
+                app.auto_accept = policy;
+                app.push_event(format!("[SET] autoAccept = {}", policy.name()));
+                let msg = match policy {
+                    AutoAccept::Off => "Incoming connection requests wait for /acceptConnection.",
+                    AutoAccept::Contacts => "Connection requests from known users are accepted automatically.",
+                    AutoAccept::Verified => "Connection requests from verified users are accepted automatically.",
+                    AutoAccept::All => "All connection requests are accepted automatically.",
+                };
+                show_lines(app, "Settings", vec![msg.to_string()]);
+            }
+            None => app.set_error("Settings", vec!["Usage: /set autoAccept off|contacts|verified|all".to_string()]),
+        },
+        other => {
+            app.set_error("Settings", vec![format!("Unknown setting '{}'. Type /set to list settings.", other)]);
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Quick-bar
+// ---------------------------------------------------------------------------
+
+fn cmd_bind(app: &mut App, rest: &str) {
+    let (key, command) = split_command(rest.trim());
+    if key.is_empty() {
+        let mut lines = vec!["Quick-bar".to_string(), String::new()];
+        for (i, cmd) in app.quickbar.iter().enumerate() {
+            let key = format!("F{}", i + 1);
+            lines.push(format!("  {:<5}{}", key, cmd.as_deref().unwrap_or("—")));
+        }
+        lines.push(String::new());
+        lines.push("Usage: /bind F<n> <command>  |  /bind F<n> F<m> (swap)  |  /unbind F<n>".to_string());
+        app.set_content("Quick-bar", lines);
+        return;
+    }
+
+    let Some(slot) = config::parse_fkey(key) else {
+        app.set_error("Quick-bar", vec![format!("'{}' is not a function key (F1–F{}).", key, QUICKBAR_KEYS)]);
+        return;
+    };
+    if command.is_empty() {
+        app.set_error("Quick-bar", vec!["Usage: /bind F<n> <command>".to_string()]);
+        return;
+    }
+
+    if let Some(other) = config::parse_fkey(command) {
+        app.quickbar.swap(slot, other);
+        app.push_event(format!("[SET] quick-bar F{} ⇄ F{}", slot + 1, other + 1));
+        show_lines(app, "Quick-bar", vec![format!("Swapped F{} and F{}.", slot + 1, other + 1)]);
+        return;
+    }
+
+    let command = if command.starts_with('/') { command.to_string() } else { format!("/{}", command) };
+    app.push_event(format!("[SET] quick-bar F{} = {}", slot + 1, command));
+    show_lines(app, "Quick-bar", vec![format!("F{} now runs {}.", slot + 1, command)]);
+    app.quickbar[slot] = Some(command);
+}
+
+fn cmd_unbind(app: &mut App, rest: &str) {
+    let Some(slot) = config::parse_fkey(rest.trim()) else {
+        app.set_error("Quick-bar", vec!["Usage: /unbind F<n>".to_string()]);
+        return;
+    };
+    app.quickbar[slot] = None;
+    app.push_event(format!("[SET] quick-bar F{} cleared", slot + 1));
+    show_lines(app, "Quick-bar", vec![format!("F{} is no longer bound.", slot + 1)]);
+}
+
+/// Short label for a quick-bar command: the command name without its slash.
+pub fn quickbar_label(command: &str) -> &str {
+    command.trim_start_matches('/').split(' ').next().unwrap_or(command)
+}
+
+fn cmd_config(app: &mut App) {
+    let startup = &app.startup;
+    let port_source = if app.listen_port != startup.listen_port {
+        Source::Session
+    } else {
+        startup.source("port")
+    };
+    let storage_dir = match &app.storage_dir {
+        Some(dir) => dir.display().to_string(),
+        None => "(library default)".to_string(),
+    };
+    let rows = [
+        ("port", app.listen_port.to_string(), port_source),
+        ("storage_dir", storage_dir, startup.source("storage_dir")),
+        ("theme", startup.theme_name.clone(), startup.source("theme")),
+        ("autostart", on_off(startup.autostart).to_string(), startup.source("autostart")),
+        ("lock", on_off(startup.locked).to_string(), startup.source("lock")),
+        (
+            "events_order",
+            if startup.events_desc { "desc" } else { "asc" }.to_string(),
+            startup.source("events_order"),
+        ),
+        ("tick", format!("{}ms", startup.tick.as_millis()), startup.source("tick")),
+        (
+            "exec",
+            if startup.exec.is_empty() { "—".to_string() } else { startup.exec.join("; ") },
+            startup.source("exec"),
+        ),
+        ("quickbar", "F1–F10 bindings (see /bind)".to_string(), startup.source("quickbar")),
+    ];
+
+    let mut lines = vec!["Configuration".to_string(), String::new()];
+    for (name, value, source) in rows {
+        lines.push(format!("  {:<12}{:<32}[{}]", name, value, source));
+    }
+    lines.push(String::new());
+    lines.push("Precedence: command line > environment (ACCORD_PORT, ACCORD_STORAGE_DIR, ACCORD_THEME, ACCORD_AUTOSTART, ACCORD_TICK_MS) > config file > defaults.".to_string());
+    if let Some(path) = config::file_path() {
+        lines.push(format!("Config file: {}", path.display()));
+    }
+    lines.push("Runtime changes made with /set are listed there.".to_string());
+    app.set_content("Config", lines);
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value {
+        "on" | "true" | "yes" => Some(true),
+        "off" | "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value { "on" } else { "off" }
 }
 
 // ---------------------------------------------------------------------------
 // Node lifecycle
 // ---------------------------------------------------------------------------
 
+/// How many ports above the configured one `/startNode` tries when it is
+/// taken (`/set portFallback`).
+const PORT_RETRIES: u16 = 10;
+
 async fn cmd_start_node(app: &mut App) -> Result<()> {
     if app.node_tx.is_some() {
         show_lines(app, "Node", vec!["Node is already running.".to_string()]);
         return Ok(());
     }
 
-    let addr_str = listen_addr(app.listen_port);
+    let mut addr_str = listen_addr(app.listen_port);
     let msg = format!("Starting node on {} …", addr_str);
     app.push_event(format!("[NODE] {}", msg));
     app.push_output(msg.clone());
 
-    let addr: Multiaddr = addr_str
-        .parse()
-        .map_err(|e: multiaddr::Error| anyhow!("Invalid listen address: {e}"))?;
+    // A port still held by a node that died uncleanly is common; walk up a
+    // few ports rather than give up on the first one.
+    let first_port = app.listen_port;
+    let mut result = start_on(&addr_str).await?;
+    if app.port_fallback {
+        let mut port = first_port;
+        while let Err(e) = &result
+            && is_addr_in_use(e)
+            && port < first_port.saturating_add(PORT_RETRIES)
+            && port < u16::MAX
+        {
+            port += 1;
+            app.push_event(format!("[NODE] WARN  Port {} in use — trying {}.", port - 1, port));
+            addr_str = listen_addr(port);
+            result = start_on(&addr_str).await?;
+            if result.is_ok() {
+                app.listen_port = port;
+            }
+        }
+    }
 
-    let node = FullNode::new(addr);
-    match node.run().await {
+    match result {
         Ok(tx) => {
             app.node_tx = Some(tx);
+            app.watcher = Some(node::spawn_watcher(app.node_events.clone(), app.storage_dir.clone()));
             app.node_status = NodeStatus::Running { addr: addr_str.clone() };
-            let ok = format!("Node started on {}.", addr_str);
-            app.push_event(format!("[NODE] {}", ok));
-            app.push_output(ok.clone());
-            show_lines(app, "Node", vec![ok]);
+            app.node_started = Some(Instant::now());
+            // Seed the header counts; the watcher reports changes from here.
+            app.peers = load_peers(app.storage()).unwrap_or_default();
+            app.connections = load_connections(app);
+            check_storage(app);
+            let mut ok = vec![format!("Node started on {}.", addr_str)];
+            if app.listen_port != first_port {
+                ok.push(format!("Port {} was in use; listening on port {} instead.", first_port, app.listen_port));
+            }
+            for line in &ok {
+                app.push_event(format!("[NODE] {}", line));
+                app.push_output(line.clone());
+            }
+            show_lines(app, "Node", ok);
         }
         Err(e) => {
             let err = format!("Failed to start node: {e}");
@@ -179,11 +1686,91 @@ async fn cmd_start_node(app: &mut App) -> Result<()> {
     Ok(())
 }
 
+/// Run a node listening on `addr_str`. The outer error is a bad address,
+/// the inner one a node that failed to start.
+async fn start_on(addr_str: &str) -> Result<Result<mpsc::Sender<FullNodeCommand>>> {
+    let addr: Multiaddr = addr_str
+        .parse()
+        .map_err(|e: multiaddr::Error| anyhow!("Invalid listen address: {e}"))?;
+    Ok(FullNode::new(addr).run().await)
+}
+
+/// Whether a failed start was the listen port being taken.
+fn is_addr_in_use(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::AddrInUse)
+            || cause.to_string().to_lowercase().contains("address already in use")
+    })
+}
+
+/// The addresses a peer can reach us on: the listen port on this
+/// machine's LAN address and on loopback, each ending in our peer id.
+fn cmd_addrs(app: &mut App) {
+    if app.node_tx.is_none() {
+        show_lines(app, "Addresses", vec!["Node is not running. Use /startNode first.".to_string()]);
+        return;
+    }
+    let mut lines = vec!["Your addresses".to_string(), String::new()];
+    if let NodeStatus::Running { addr } = &app.node_status {
+        lines.push(format!("  listening on : {}", addr));
+    }
+    lines.push(String::new());
+    lines.extend(dialable_addrs(app).iter().map(|a| format!("  {}", a)));
+    lines.push(String::new());
+    match &app.local_peer_id {
+        Some(_) => lines.push("  A peer on another network can reach you on one of these.".to_string()),
+        None => lines.push(
+            "  The node has not announced its peer id yet, so the /p2p/ part is missing; try again shortly.".to_string(),
+        ),
+    }
+    app.set_content("Addresses", lines);
+}
+
+/// Multiaddrs a peer can dial us on: the primary LAN address first, then
+/// loopback for a second instance on this machine.
+fn dialable_addrs(app: &App) -> Vec<String> {
+    let suffix = app.local_peer_id.as_ref().map(|id| format!("/p2p/{}", id)).unwrap_or_default();
+    let mut ips = Vec::new();
+    if let Some(ip) = lan_ip() {
+        ips.push(ip);
+    }
+    ips.push(std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST));
+    ips.iter()
+        .map(|ip| {
+            let proto = if ip.is_ipv4() { "ip4" } else { "ip6" };
+            format!("/{}/{}/tcp/{}{}", proto, ip, app.listen_port, suffix)
+        })
+        .collect()
+}
+
+/// The address of the interface that carries outgoing traffic. Connecting a
+/// UDP socket only picks a route; nothing is sent.
+fn lan_ip() -> Option<std::net::IpAddr> {
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:9").ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_unspecified() && !ip.is_loopback()).then_some(ip)
+}
+
+/// How long `/stopNode` waits for the node to wind down.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+
 async fn cmd_stop_node(app: &mut App) -> Result<()> {
     match app.node_tx.take() {
         Some(tx) => {
-            let _ = tx.send(FullNodeCommand::Shutdown).await;
-            app.node_status = NodeStatus::Stopped;
+            // Wait for the node to drop its end, i.e. to have closed its
+            // listeners, so the port is free for a restart or next launch.
+            if tx.send(FullNodeCommand::Shutdown).await.is_ok()
+                && tokio::time::timeout(SHUTDOWN_GRACE, tx.closed()).await.is_err()
+            {
+                app.push_event(format!(
+                    "[NODE] WARN  Node did not finish shutting down within {}s.",
+                    SHUTDOWN_GRACE.as_secs()
+                ));
+            }
+            forget_node(app);
             app.push_event("[NODE] Stopped.");
             app.push_output("Node stopped.".to_string());
             show_lines(app, "Node", vec!["Node stopped.".to_string()]);
@@ -195,6 +1782,28 @@ async fn cmd_stop_node(app: &mut App) -> Result<()> {
     Ok(())
 }
 
+/// Drop everything that belongs to a running node, after `node_tx` is gone.
+fn forget_node(app: &mut App) {
+    if let Some(watcher) = app.watcher.take() {
+        watcher.abort();
+    }
+    app.local_peer_id = None;
+    app.node_status = NodeStatus::Stopped;
+    app.node_started = None;
+}
+
+/// If the node's end of the channel is closed although we never stopped it,
+/// mark it stopped and say so. Returns whether it had died.
+fn node_died(app: &mut App) -> bool {
+    if !app.node_tx.as_ref().is_some_and(|tx| tx.is_closed()) {
+        return false;
+    }
+    app.node_tx = None;
+    forget_node(app);
+    app.push_event("[NODE] ERROR  Node stopped unexpectedly — run /startNode to start it again.");
+    true
+}
+
 async fn cmd_restart_node(app: &mut App) -> Result<()> {
     app.push_event("[NODE] Restarting…");
     cmd_stop_node(app).await?;
@@ -203,6 +1812,9 @@ async fn cmd_restart_node(app: &mut App) -> Result<()> {
     Ok(())
 }
 
+/// Ports below this need elevated privileges to bind on most systems.
+const PRIVILEGED_PORTS: u16 = 1024;
+
 async fn cmd_port(app: &mut App, rest: &str) -> Result<()> {
     let arg = rest.trim();
     if arg.is_empty() {
@@ -223,6 +1835,25 @@ async fn cmd_port(app: &mut App, rest: &str) -> Result<()> {
     }
 
     let old_port = app.listen_port;
+    if new_port == old_port {
+        let msg = if app.node_tx.is_some() {
+            format!("Already listening on port {}.", new_port)
+        } else {
+            format!("Port is already {}. Use /startNode to start the node.", new_port)
+        };
+        show_lines(app, "Port", vec![msg]);
+        return Ok(());
+    }
+
+    if new_port < PRIVILEGED_PORTS {
+        let warn = format!(
+            "Port {} is privileged (below {}); binding it usually needs root.",
+            new_port, PRIVILEGED_PORTS
+        );
+        app.push_event(format!("[NODE] WARN  {}", warn));
+        app.push_output(warn);
+    }
+
     app.listen_port = new_port;
     app.push_event(format!("[NODE] Port changed: {} → {}", old_port, new_port));
     app.push_output(format!("Port changed to {}. Restarting node…", new_port));
@@ -234,37 +1865,261 @@ async fn cmd_port(app: &mut App, rest: &str) -> Result<()> {
 // Sync
 // ---------------------------------------------------------------------------
 
-fn cmd_sync(app: &mut App) {
-    if app.node_tx.is_none() {
-        show_lines(app, "Sync", vec!["Node is not running. Use /startNode first.".to_string()]);
-        return;
+fn cmd_sync(app: &mut App) {
+    if app.node_tx.is_none() {
+        show_lines(app, "Sync", vec!["Node is not running. Use /startNode first.".to_string()]);
+        return;
+    }
+    let msg = "Sync is continuous — the node syncs automatically with peers via gossipsub.";
+    app.push_event("[SYNC] Manual sync requested.");
+    app.push_output(msg.to_string());
+    show_lines(app, "Sync", vec![msg.to_string()]);
+}
+
+// ---------------------------------------------------------------------------
+// Peers
+// ---------------------------------------------------------------------------
+
+fn cmd_peers(app: &mut App, rest: &str) -> Result<()> {
+    let follow = match rest.trim() {
+        "" => false,
+        "follow" => true,
+        other => {
+            show_lines(app, "Peers", vec![format!("Unknown option '{}'. Usage: /peers [follow]", other)]);
+            return Ok(());
+        }
+    };
+
+    let peers = load_peers(app.storage()).unwrap_or_default();
+    app.peers = peers.clone();
+    app.peers_follow = follow;
+    app.push_event(format!("[PEERS] Refreshed ({} known).", peers.len()));
+    app.push_output(format!("Peers: {} known.", peers.len()));
+    show_peers(app);
+    Ok(())
+}
+
+/// How often a followed peers view re-reads the stored peer list.
+const PEERS_FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// While `/peers follow` is on screen, re-read the stored peers now and then
+/// so the list also catches peers that were removed. Called on every tick of
+/// the main loop.
+pub fn follow_peers(app: &mut App) {
+    if !app.peers_follow || app.content_kind != ViewKind::Peers {
+        return;
+    }
+    if app.peers_loaded.is_some_and(|at| at.elapsed() < PEERS_FOLLOW_INTERVAL) {
+        return;
+    }
+    app.peers_loaded = Some(Instant::now());
+    // Peers seen live over mDNS count even before they are stored.
+    let mut peers = load_peers(app.storage()).unwrap_or_default();
+    for peer in app.live_peers.keys() {
+        if !peers.contains(peer) {
+            peers.push(peer.clone());
+        }
+    }
+    peers.sort();
+    if peers != app.peers {
+        app.peers = peers;
+        show_peers(app);
+    }
+}
+
+/// Render `app.peers` into the content area, in lexical order.
+pub fn show_peers(app: &mut App) {
+    app.peers.sort();
+    let mut lines = vec![format!("Known peers  ({})", app.peers.len())];
+    if app.peers_follow {
+        lines.push("  Following — new peers appear as they are discovered.".to_string());
+    }
+    lines.push(String::new());
+    if app.peers.is_empty() {
+        lines.push("  No peers discovered yet. Start the node and wait for mDNS/Kademlia.".to_string());
+    } else {
+        for (i, p) in app.peers.iter().enumerate() {
+            lines.push(format!("  {:>3}.  {}", i + 1, p));
+        }
+    }
+    let scroll = app.content_scroll;
+    let refreshing = app.content_kind == ViewKind::Peers;
+    app.set_content("Peers", lines);
+    // Keep the reader's place when the view is re-rendered in place.
+    if refreshing {
+        app.content_scroll = scroll;
+    }
+}
+
+fn cmd_net_peers(app: &mut App) {
+    app.push_output(format!("Network peers: {} live.", app.live_peers.len()));
+    show_net_peers(app);
+}
+
+/// Render `app.live_peers` into the content area: one foldable entry per
+/// peer id, its addresses inside. Folds keep their open state across
+/// refreshes.
+pub fn show_net_peers(app: &mut App) {
+    let refreshing = app.content_kind == ViewKind::NetPeers;
+    let was_open: Vec<String> = if refreshing {
+        app.content_folds.iter().filter(|f| f.open).map(|f| f.label.clone()).collect()
+    } else {
+        Vec::new()
+    };
+    let addresses: usize = app.live_peers.values().map(Vec::len).sum();
+
+    let mut lines = vec![
+        format!("Network peers  ({} peers, {} addresses)", app.live_peers.len(), addresses),
+        String::new(),
+    ];
+    let mut folds = Vec::new();
+    if app.live_peers.is_empty() {
+        lines.push("  No peers seen on the network yet. Start the node and wait for mDNS.".to_string());
+    }
+    for (peer, addrs) in &app.live_peers {
+        folds.push(Fold {
+            start: lines.len(),
+            len: addrs.len().max(1),
+            label: peer.clone(),
+            unit: if addrs.len() == 1 { "address" } else { "addresses" },
+            open: was_open.contains(peer),
+        });
+        if addrs.is_empty() {
+            // Every fold needs a line of its own to sit on.
+            lines.push("      (no address reported)".to_string());
+        }
+        lines.extend(addrs.iter().map(|a| format!("      {}", a)));
+    }
+
+    let scroll = app.content_scroll;
+    app.set_content("Network Peers", lines);
+    app.content_folds = folds;
+    if refreshing {
+        app.content_scroll = scroll;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Ping
+// ---------------------------------------------------------------------------
+
+/// How long `/ping` waits for the echo before reporting the peer silent.
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Send a `ping` plugin message and wait, on the main loop's ticks, for the
+/// peer's client to echo it back as a `pong`. The library has no ping of its
+/// own.
+async fn cmd_ping(app: &mut App, rest: &str) -> Result<()> {
+    let nick = rest.trim();
+    if nick.is_empty() {
+        show_lines(app, "Ping", vec!["Usage: /ping <nick>".to_string()]);
+        return Ok(());
+    }
+    let Some(to_id) = resolve_nick(app, nick) else {
+        show_lines(app, "Ping", vec![format!("No user found with nick '{}'. Use /users to see known users.", nick)]);
+        return Ok(());
+    };
+    if refuse_if_read_only(app, "Ping") {
+        return Ok(());
+    }
+    let Some(tx) = app.node_tx.clone() else {
+        show_lines(app, "Ping", vec!["Node is not running. Use /startNode first.".to_string()]);
+        return Ok(());
+    };
+    let local_user = load_local_user(app.storage())
+        .map_err(|_| anyhow!("No local user — run /user first"))?;
+
+    let nonce = ping_nonce();
+    let msg = Message::new(local_user.id, to_id.as_str(), "ping", serde_json::json!({ "nonce": nonce }));
+    let data = serde_json::to_vec(&msg)?;
+    let (reply_tx, reply_rx) = oneshot::channel();
+    tx.send(FullNodeCommand::StoreMessage { data, reply: reply_tx })
+        .await
+        .map_err(|_| anyhow!("Node channel closed"))?;
+    if let Err(e) = reply_rx.await? {
+        app.push_event(format!("[PING] Send to {} failed: {e}", nick));
+        show_lines(app, "Ping", vec![format!("Error sending ping: {e}")]);
+        return Ok(());
+    }
+
+    app.pings.push(PendingPing { nonce, to_id, nick: nick.to_string(), since: Instant::now() });
+    app.push_event(format!("[PING] → {}", nick));
+    show_lines(app, "Ping", vec![format!(
+        "Ping sent to {} — waiting up to {}s for the echo…",
+        nick,
+        PING_TIMEOUT.as_secs()
+    )]);
+    Ok(())
+}
+
+/// A fresh nonce for a ping: the current time in nanoseconds, in hex.
+fn ping_nonce() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    format!("{:x}", nanos)
+}
+
+/// Echo a peer's ping back as a pong, in the background so receiving is not
+/// held up by the node.
+fn answer_ping(app: &mut App, ping: &Message, nick: &str) {
+    let pong = Message::new(ping.to.clone(), ping.from.as_str(), "pong", ping.plugin_body.clone());
+    if store_in_background(app, &pong) {
+        app.push_event(format!("[PING] ← ping from {} — answering.", nick));
     }
-    let msg = "Sync is continuous — the node syncs automatically with peers via gossipsub.";
-    app.push_event("[SYNC] Manual sync requested.");
-    app.push_output(msg.to_string());
-    show_lines(app, "Sync", vec![msg.to_string()]);
 }
 
-// ---------------------------------------------------------------------------
-// Peers
-// ---------------------------------------------------------------------------
+/// Hand an automatic reply (pong, receipt) to the node without waiting for
+/// it. Returns whether it was handed over.
+fn store_in_background(app: &App, message: &Message) -> bool {
+    let Some(tx) = app.node_tx.clone() else {
+        return false;
+    };
+    let Ok(data) = serde_json::to_vec(message) else {
+        return false;
+    };
+    tokio::spawn(async move {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if tx.send(FullNodeCommand::StoreMessage { data, reply: reply_tx }).await.is_ok() {
+            let _ = reply_rx.await;
+        }
+    });
+    true
+}
 
-fn cmd_peers(app: &mut App) -> Result<()> {
-    let peers = load_peers(None).unwrap_or_default();
-    app.peers = peers.clone();
-    app.push_event(format!("[PEERS] Refreshed ({} known).", peers.len()));
-    app.push_output(format!("Peers: {} known.", peers.len()));
+/// Match a pong to the ping it answers and report the round trip. Returns
+/// whether it answered one of ours.
+fn settle_ping(app: &mut App, pong: &Message, nick: &str) -> bool {
+    let nonce = pong.plugin_body.get("nonce").and_then(|n| n.as_str());
+    let Some(i) = app.pings.iter().position(|p| Some(p.nonce.as_str()) == nonce && p.to_id == pong.from) else {
+        return false;
+    };
+    let ping = app.pings.remove(i);
+    let msg = format!("Pong from {} after {} ms — the peer is alive.", nick, ping.since.elapsed().as_millis());
+    app.push_event(format!("[PING] ← {}", msg));
+    app.push_output(msg.clone());
+    if app.content_title.trim() == "Ping" {
+        app.content_lines.push(format!("  {}", msg));
+    }
+    true
+}
 
-    let mut lines = vec![format!("Known peers  ({})", peers.len()), String::new()];
-    if peers.is_empty() {
-        lines.push("  No peers discovered yet. Start the node and wait for mDNS/Kademlia.".to_string());
-    } else {
-        for (i, p) in peers.iter().enumerate() {
-            lines.push(format!("  {:>3}.  {}", i + 1, p));
+/// Report pings that went unanswered for `PING_TIMEOUT`. Called on every
+/// tick of the main loop.
+pub fn check_pings(app: &mut App) {
+    let (expired, waiting): (Vec<PendingPing>, Vec<PendingPing>) =
+        std::mem::take(&mut app.pings).into_iter().partition(|p| p.since.elapsed() >= PING_TIMEOUT);
+    app.pings = waiting;
+    for ping in expired {
+        let msg = format!(
+            "No reply from {} within {}s — the peer may be offline or the connection stale.",
+            ping.nick,
+            PING_TIMEOUT.as_secs()
+        );
+        app.push_event(format!("[PING] ⚠ {}", msg));
+        app.push_output(msg.clone());
+        if app.content_title.trim() == "Ping" {
+            app.content_lines.push(format!("  ⚠ {}", msg));
         }
     }
-    app.set_content("Peers", lines);
-    Ok(())
 }
 
 // ---------------------------------------------------------------------------
@@ -273,12 +2128,7 @@ fn cmd_peers(app: &mut App) -> Result<()> {
 
 fn cmd_nick(app: &mut App, rest: &str) -> Result<()> {
     let new_name = rest.trim();
-    if new_name.is_empty() {
-        show_lines(app, "Nick", vec!["Usage: /nick <new_name>".to_string()]);
-        return Ok(());
-    }
-
-    let mut user = match load_local_user(None) {
+    let mut user = match load_local_user(app.storage()) {
         Ok(u) => u,
         Err(_) => {
             show_lines(app, "Nick", vec!["No local user found. Use /user to create one first.".to_string()]);
@@ -286,18 +2136,44 @@ fn cmd_nick(app: &mut App, rest: &str) -> Result<()> {
         }
     };
 
+    // Bare /nick shows the current name, like /port and /user do.
+    if new_name.is_empty() {
+        let name = user.meta.display_name.as_deref().unwrap_or("(unnamed)");
+        show_lines(app, "Nick", vec![format!("Your display name: {}  |  Usage: /nick <new_name>", name)]);
+        return Ok(());
+    }
+
+    if refuse_if_read_only(app, "Nick") {
+        return Ok(());
+    }
     let old_name = user.meta.display_name.clone().unwrap_or_else(|| "(unnamed)".to_string());
     user.meta.display_name = Some(new_name.to_string());
-    save_local_user(&user, None)?;
+    if let Err(e) = save_local_user(&user, app.storage()) {
+        if storage_write_failed(app, "Nick", &e) {
+            return Ok(());
+        }
+        return Err(e);
+    }
 
     if let Some(local) = app.users.iter_mut().find(|u| u.is_local()) {
         local.meta.display_name = Some(new_name.to_string());
     }
+    app.nick_cache.clear();
 
     let msg = format!("Display name changed: {} → {}", old_name, new_name);
     app.push_event(format!("[NICK] {} → {}", old_name, new_name));
     app.push_output(msg.clone());
-    show_lines(app, "Nick", vec![msg]);
+    let mut lines = vec![msg];
+    // The node keeps its own copy of the local user and has no command to
+    // update it, so it goes on announcing the old name until restarted.
+    if app.node_tx.is_some() {
+        let note = "The running node still announces your old name to peers until it restarts — use /restartNode to apply it now.";
+        app.push_event(format!("[NICK] ⚠ {}", note));
+        app.push_output(note.to_string());
+        lines.push(String::new());
+        lines.push(format!("  ⚠ {}", note));
+    }
+    show_lines(app, "Nick", lines);
     Ok(())
 }
 
@@ -310,7 +2186,7 @@ async fn cmd_user(app: &mut App, rest: &str) -> Result<()> {
 
     // /user <nick>  → look up by display name
     if !arg.is_empty() {
-        if let Some(id) = resolve_nick(arg) {
+        if let Some(id) = resolve_nick(app, arg) {
             return cmd_show_user_by_id(app, &id).await;
         }
         // Nick not found — treat as display name for a new user.
@@ -326,9 +2202,9 @@ async fn cmd_user(app: &mut App, rest: &str) -> Result<()> {
 
     // Show existing local user if no arg.
     if arg.is_empty() {
-        match load_local_user(None) {
+        match load_local_user(app.storage()) {
             Ok(user) => {
-                let lines = user_lines(&user);
+                let lines = user_lines(&user, &app.trust);
                 app.set_content("User", lines);
                 return Ok(());
             }
@@ -339,6 +2215,9 @@ async fn cmd_user(app: &mut App, rest: &str) -> Result<()> {
     }
 
     // Create user.
+    if refuse_if_read_only(app, "User") {
+        return Ok(());
+    }
     let meta = UserMeta {
         display_name: if arg.is_empty() { None } else { Some(arg.to_string()) },
         ..Default::default()
@@ -351,15 +2230,17 @@ async fn cmd_user(app: &mut App, rest: &str) -> Result<()> {
 
     match reply_rx.await? {
         Ok(user) => {
+            app.local_id = Some(user.id.clone());
             let name = user.meta.display_name.as_deref().unwrap_or("(unnamed)");
             app.push_event(format!("[USER] Created: {} ({})", name, truncate_id(&user.id, 16)));
             app.push_output(format!("User created: {}", name));
-            let lines = user_lines(&user);
+            let lines = user_lines(&user, &app.trust);
             if !app.users.iter().any(|u| u.id == user.id) {
                 app.users.push(user);
             }
             app.set_content("User", lines);
         }
+        Err(e) if storage_write_failed(app, "User", &e) => {}
         Err(e) => {
             app.push_event(format!("[USER] Create failed: {e}"));
             show_lines(app, "User", vec![format!("Error creating user: {e}")]);
@@ -378,14 +2259,13 @@ async fn cmd_show_user_by_id(app: &mut App, id: &str) -> Result<()> {
         }
     };
 
-    let (reply_tx, reply_rx) = oneshot::channel();
-    tx.send(FullNodeCommand::GetUser { id: id.to_string(), reply: reply_tx })
-        .await
-        .map_err(|_| anyhow!("Node channel closed"))?;
-
-    match reply_rx.await? {
+    match fetch_user(&tx, id).await? {
         Ok(user) => {
-            let lines = user_lines(&user);
+            check_key_change(app, &user);
+            let mut lines = user_lines(&user, &app.trust);
+            if let Some(&secs) = app.clock_skew.get(&user.id) {
+                lines.push(format!("  clock skew  : {} (as of their last message)", skew_label(secs)));
+            }
             app.set_content("User", lines);
         }
         Err(e) => {
@@ -396,82 +2276,410 @@ async fn cmd_show_user_by_id(app: &mut App, id: &str) -> Result<()> {
     Ok(())
 }
 
-async fn cmd_users(app: &mut App) -> Result<()> {
-    let tx = match &app.node_tx {
-        Some(tx) => tx.clone(),
-        None => {
-            // Fallback: read from filesystem.
-            let ids = list_known_users(None).unwrap_or_default();
-            let mut lines = vec![format!("Known users  ({})", ids.len()), String::new()];
-            if ids.is_empty() {
-                lines.push("  No remote users on record.".to_string());
-            } else {
-                for id in &ids {
-                    let name = load_known_user(id, None)
-                        .ok()
-                        .and_then(|m| m.display_name)
-                        .unwrap_or_else(|| "(unnamed)".to_string());
-                    lines.push(format!("  {}  {}", name, id));
-                }
+/// One entry of `/users`, merged from the node and from storage.
+struct UserRow {
+    id: String,
+    name: Option<String>,
+    local: bool,
+    /// Known only for users the node reported.
+    public_key: Option<String>,
+    on_node: bool,
+    on_disk: bool,
+    /// The stored record exists but could not be read.
+    unreadable: bool,
+}
+
+impl UserRow {
+    fn origin(&self) -> &'static str {
+        match (self.on_node, self.on_disk) {
+            (true, true) => "node + disk",
+            (true, false) => "node only",
+            _ => "disk only",
+        }
+    }
+}
+
+/// Add a user found in storage, or mark the node's entry for them as stored.
+fn merge_disk_user(rows: &mut Vec<UserRow>, id: String, name: Option<String>, local: bool, unreadable: bool) {
+    match rows.iter_mut().find(|r| r.id == id) {
+        Some(row) => {
+            row.on_disk = true;
+            if row.name.is_none() {
+                row.name = name;
             }
-            app.set_content("Users", lines);
+        }
+        None => rows.push(UserRow { id, name, local, public_key: None, on_node: false, on_disk: true, unreadable }),
+    }
+}
+
+/// Every user the node knows and every user on disk, once each. Users the
+/// node has not loaded yet (e.g. created while it was stopped) still show.
+async fn cmd_users(app: &mut App, rest: &str) -> Result<()> {
+    let by_activity = match rest.trim() {
+        "" | "sort:name" => false,
+        "sort:activity" => true,
+        other => {
+            app.set_error("Users", vec![format!("Unknown option '{}'. Usage: /users [sort:activity]", other)]);
             return Ok(());
         }
     };
 
-    let (reply_tx, reply_rx) = oneshot::channel();
-    tx.send(FullNodeCommand::GetUsers { reply: reply_tx })
-        .await
-        .map_err(|_| anyhow!("Node channel closed"))?;
-
-    match reply_rx.await? {
-        Ok(users) => {
-            app.users = users.clone();
-            app.push_event(format!("[USERS] Refreshed ({} found).", users.len()));
-            app.push_output(format!("Users: {} found.", users.len()));
-            let mut lines = vec![format!("Known users  ({})", users.len()), String::new()];
-            if users.is_empty() {
-                lines.push("  No remote users discovered yet.".to_string());
-            } else {
+    let mut rows = Vec::new();
+    let mut notes = Vec::new();
+    let running = app.node_tx.is_some();
+    if let Some(tx) = app.node_tx.clone() {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        tx.send(FullNodeCommand::GetUsers { reply: reply_tx })
+            .await
+            .map_err(|_| anyhow!("Node channel closed"))?;
+        match reply_rx.await? {
+            Ok(users) => {
                 for u in &users {
-                    let label = if u.is_local() { "LOCAL " } else { "REMOTE" };
-                    let name = u.meta.display_name.as_deref().unwrap_or("(unnamed)");
-                    lines.push(format!("  [{}]  {}  —  {}", label, name, truncate_id(&u.id, 24)));
+                    check_key_change(app, u);
+                    rows.push(UserRow {
+                        id: u.id.clone(),
+                        name: u.meta.display_name.clone(),
+                        local: u.is_local(),
+                        public_key: Some(u.public_key.to_string()),
+                        on_node: true,
+                        on_disk: false,
+                        unreadable: false,
+                    });
                 }
+                app.users = users;
+            }
+            Err(e) => {
+                app.push_event(format!("[USERS] Fetch failed: {e}"));
+                notes.push(format!("  ⚠ The node could not list users ({e}); showing stored users only."));
             }
-            app.set_content("Users", lines);
         }
-        Err(e) => {
-            app.push_event(format!("[USERS] Fetch failed: {e}"));
-            show_lines(app, "Users", vec![format!("Error fetching users: {e}")]);
+    }
+
+    if let Ok(local) = load_local_user(app.storage()) {
+        merge_disk_user(&mut rows, local.id, local.meta.display_name, true, false);
+    }
+    let mut issues = Vec::new();
+    for id in list_known_users(app.storage()).unwrap_or_default() {
+        match load_known_user(&id, app.storage()) {
+            Ok(meta) => merge_disk_user(&mut rows, id, meta.display_name, false, false),
+            Err(e) => {
+                issues.push(StorageIssue { record: "user", id: id.clone(), error: e.to_string() });
+                merge_disk_user(&mut rows, id, None, false, true);
+            }
         }
     }
+    app.set_storage_issues("user", issues);
+
+    // Local user first, then by name; the activity sort keeps that order for ties.
+    rows.sort_by_cached_key(|r| (!r.local, r.name.is_none(), r.name.as_ref().map(|n| n.to_lowercase()), r.id.clone()));
+    if by_activity {
+        rows.sort_by_key(|r| std::cmp::Reverse(app.message_counts.get(&r.id)));
+    }
+    cache_nicks(app, rows.iter().map(|r| (r.name.as_deref(), r.id.as_str())));
+    let disk_only = rows.iter().filter(|r| !r.on_node).count();
+    app.push_event(format!("[USERS] Refreshed ({} found).", rows.len()));
+    if running {
+        app.push_output(format!("Users: {} found, {} of them only on disk.", rows.len(), disk_only));
+    } else {
+        app.push_output(format!("Users: {} found.", rows.len()));
+    }
 
+    let mut lines = vec![format!("Known users  ({})", rows.len()), String::new()];
+    if rows.is_empty() {
+        lines.push("  No users on record yet.".to_string());
+    }
+    for r in &rows {
+        let label = if r.local { "LOCAL " } else { "REMOTE" };
+        let name = match (&r.name, r.unreadable) {
+            (_, true) => "(unreadable)",
+            (Some(name), false) => name.as_str(),
+            (None, false) => "(unnamed)",
+        };
+        let mark = r.public_key.as_ref().map_or("", |key| verified_mark(app.trust.status(&r.id, key)));
+        let count = if r.local { String::new() } else { format!("  {}", message_count(app.message_counts.get(&r.id))) };
+        // Where each entry came from only tells something while the node runs.
+        let origin = if running { format!("  [{}]", r.origin()) } else { String::new() };
+        lines.push(format!("  [{}]  {}  —  {}{}{}{}", label, name, id_label(app, &r.id, 24), count, mark, origin));
+    }
+    if !notes.is_empty() {
+        lines.push(String::new());
+        lines.extend(notes);
+    }
+    push_storage_notice(app, &mut lines, "user");
+    app.set_content("Users", lines);
     Ok(())
 }
 
-fn user_lines(user: &User) -> Vec<String> {
+/// The local identity and node at a glance. Unlike `/user`, never creates a
+/// user.
+fn cmd_whoami(app: &mut App) {
+    let mut lines = match load_local_user(app.storage()) {
+        Ok(user) => {
+            let name = user.meta.display_name.as_deref().unwrap_or("(unnamed)");
+            vec![
+                format!("[LOCAL]  {}", name),
+                format!("  id          : {}", user.id),
+                format!("  public_key  : {}", user.public_key),
+                format!("  fingerprint : {}", trust::fingerprint(&user.public_key)),
+            ]
+        }
+        Err(_) => vec![
+            "No local user yet.".to_string(),
+            "  Use /user [display_name] to create one.".to_string(),
+        ],
+    };
+    lines.push(String::new());
+    lines.push(format!("  node        : {}", app.node_status));
+    lines.push(format!("  port        : {}", app.listen_port));
+    if let Some(peer_id) = &app.local_peer_id {
+        lines.push(format!("  peer id     : {}", peer_id));
+    }
+    if app.node_tx.is_some()
+        && let Some(addr) = dialable_addrs(app).first()
+    {
+        lines.push(format!("  dial me at  : {}  (all: /addrs)", addr));
+    }
+    app.set_content("User", lines);
+}
+
+fn message_count(n: u64) -> String {
+    if n == 1 { "(1 msg)".to_string() } else { format!("({} msgs)", n) }
+}
+
+fn cmd_identity(app: &mut App, rest: &str) {
+    let (sub, _) = split_command(rest.trim());
+    match sub {
+        "" | "list" => {
+            let mut lines = vec!["Local identities".to_string(), String::new()];
+            match load_local_user(app.storage()) {
+                Ok(user) => {
+                    let name = user.meta.display_name.as_deref().unwrap_or("(unnamed)");
+                    lines.push(format!("  * {}  —  {}  (active)", name, id_label(app, &user.id, 24)));
+                }
+                Err(_) => lines.push("  No local user yet. Use /user to create one.".to_string()),
+            }
+            app.set_content("Identity", lines);
+        }
+        other => {
+            app.set_error("Identity", vec![format!("Unknown subcommand '{}'. Usage: /identity list", other)]);
+        }
+    }
+}
+
+fn user_lines(user: &User, trust: &TrustStore) -> Vec<String> {
     let role = if user.is_local() { "LOCAL" } else { "REMOTE" };
     let name = user.meta.display_name.as_deref().unwrap_or("(unnamed)");
+    let public_key = user.public_key.to_string();
+    let status = trust.status(&user.id, &public_key);
+    let mut lines = vec![
+        format!("[{}]  {}{}", role, name, verified_mark(status)),
+        format!("  id          : {}", user.id),
+        format!("  public_key  : {}", public_key),
+        format!("  fingerprint : {}", trust::fingerprint(&public_key)),
+    ];
+    if status == KeyStatus::Changed {
+        lines.push(String::new());
+        lines.extend(key_changed_warning(name));
+    }
+    lines
+}
+
+fn verified_mark(status: KeyStatus) -> &'static str {
+    match status {
+        KeyStatus::Unverified => "",
+        KeyStatus::Verified => "  ✓",
+        KeyStatus::Changed => "  ⚠ KEY CHANGED",
+    }
+}
+
+fn key_changed_warning(name: &str) -> Vec<String> {
     vec![
-        format!("[{}]  {}", role, name),
-        format!("  id         : {}", user.id),
-        format!("  public_key : {}", user.public_key),
+        format!("  ⚠ WARNING: {}'s public key has changed since you last saw it!", name),
+        "  ⚠ This may be a key rotation — or someone impersonating them.".to_string(),
+        format!("  ⚠ Compare /fingerprint {} out of band, then /acceptKey {}.", name, name),
     ]
 }
 
+/// Remember a remote user's key on first sight, and record a `[TRUST]` event
+/// if they now present a different key than the one on record.
+fn check_key_change(app: &mut App, user: &User) {
+    if user.is_local() {
+        return;
+    }
+    let public_key = user.public_key.to_string();
+    let name = user.meta.display_name.as_deref().unwrap_or("(unnamed)");
+    if app.trust.status(&user.id, &public_key) == KeyStatus::Changed {
+        app.push_event(format!(
+            "[TRUST] ⚠ KEY CHANGED for {} ({}) — /acceptKey {} once verified.",
+            name,
+            truncate_id(&user.id, 16),
+            name
+        ));
+    } else if let Err(e) = app.trust.observe(&user.id, &public_key) {
+        app.push_event(format!("[TRUST] Could not record key for {}: {e}", name));
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Trust
+// ---------------------------------------------------------------------------
+
+async fn cmd_fingerprint(app: &mut App, rest: &str) -> Result<()> {
+    let nick = rest.trim();
+    if nick.is_empty() {
+        let lines = match load_local_user(app.storage()) {
+            Ok(user) => vec![
+                "Your fingerprint".to_string(),
+                String::new(),
+                format!("  {}", trust::fingerprint(&user.public_key.to_string())),
+                String::new(),
+                "  Read this to your peer over a trusted channel (in person, phone call).".to_string(),
+            ],
+            Err(_) => vec!["No local user found. Use /user to create one first.".to_string()],
+        };
+        app.set_content("Fingerprint", lines);
+        return Ok(());
+    }
+
+    let user = match fetch_user_by_nick(app, "Fingerprint", nick).await? {
+        Some(user) => user,
+        None => return Ok(()),
+    };
+    check_key_change(app, &user);
+
+    let public_key = user.public_key.to_string();
+    let status = app.trust.status(&user.id, &public_key);
+    let mut lines = vec![
+        format!("Fingerprint for {}{}", nick, verified_mark(status)),
+        String::new(),
+        format!("  {}", trust::fingerprint(&public_key)),
+        String::new(),
+    ];
+    match status {
+        KeyStatus::Verified => lines.push(format!("  ✓ You verified {}'s key.", nick)),
+        KeyStatus::Changed => lines.extend(key_changed_warning(nick)),
+        KeyStatus::Unverified => {
+            lines.push(format!("  Ask {} to read out their /fingerprint over a trusted channel.", nick));
+            lines.push(format!("  If it matches, run /verify {}.", nick));
+        }
+    }
+    app.set_content("Fingerprint", lines);
+    Ok(())
+}
+
+async fn cmd_verify(app: &mut App, rest: &str) -> Result<()> {
+    let nick = rest.trim();
+    if nick.is_empty() {
+        show_lines(app, "Verify", vec!["Usage: /verify <nick>".to_string()]);
+        return Ok(());
+    }
+
+    let user = match fetch_user_by_nick(app, "Verify", nick).await? {
+        Some(user) => user,
+        None => return Ok(()),
+    };
+
+    let public_key = user.public_key.to_string();
+    let fp = trust::fingerprint(&public_key);
+    app.trust.verify(&user.id, &public_key)?;
+    app.push_event(format!("[TRUST] ✓ Verified {} ({}).", nick, truncate_id(&user.id, 16)));
+    app.push_output(format!("Verified {} — fingerprint {}.", nick, fp));
+    app.set_content("Verify", vec![
+        format!("✓ {} verified", nick),
+        String::new(),
+        format!("  id          : {}", user.id),
+        format!("  fingerprint : {}", fp),
+        String::new(),
+        "  You will be warned if this peer's key ever changes.".to_string(),
+    ]);
+    Ok(())
+}
+
+async fn cmd_accept_key(app: &mut App, rest: &str) -> Result<()> {
+    let nick = rest.trim();
+    if nick.is_empty() {
+        show_lines(app, "Accept Key", vec!["Usage: /acceptKey <nick>".to_string()]);
+        return Ok(());
+    }
+
+    let user = match fetch_user_by_nick(app, "Accept Key", nick).await? {
+        Some(user) => user,
+        None => return Ok(()),
+    };
+
+    let public_key = user.public_key.to_string();
+    if app.trust.status(&user.id, &public_key) != KeyStatus::Changed {
+        show_lines(app, "Accept Key", vec![format!("{}'s key has not changed — nothing to accept.", nick)]);
+        return Ok(());
+    }
+
+    let unverified = app.trust.accept_key(&user.id, &public_key)?;
+    app.push_event(format!("[TRUST] Accepted new key for {} ({}).", nick, truncate_id(&user.id, 16)));
+    let mut lines = vec![
+        format!("New key accepted for {}", nick),
+        String::new(),
+        format!("  fingerprint : {}", trust::fingerprint(&public_key)),
+    ];
+    if unverified {
+        lines.push(String::new());
+        lines.push(format!("  Your earlier verification of {} was removed; /verify them again once checked.", nick));
+    }
+    app.set_content("Accept Key", lines);
+    Ok(())
+}
+
+/// Resolve a nick and fetch the full user record from the node, reporting
+/// any failure in the content area under `title`.
+async fn fetch_user_by_nick(app: &mut App, title: &str, nick: &str) -> Result<Option<User>> {
+    let id = match resolve_nick(app, nick) {
+        Some(id) => id,
+        None => {
+            show_lines(app, title, vec![format!(
+                "No user found with nick '{}'. Use /users to see known users.", nick
+            )]);
+            return Ok(None);
+        }
+    };
+
+    let tx = match &app.node_tx {
+        Some(tx) => tx.clone(),
+        None => {
+            show_lines(app, title, vec!["Node is not running. Use /startNode first.".to_string()]);
+            return Ok(None);
+        }
+    };
+
+    match fetch_user(&tx, &id).await? {
+        Ok(user) => Ok(Some(user)),
+        Err(e) => {
+            show_lines(app, title, vec![format!("User not found: {e}")]);
+            Ok(None)
+        }
+    }
+}
+
+/// Ask the node for one user's record. The outer error means the node is
+/// unreachable, the inner one that the node could not find the user.
+async fn fetch_user(tx: &mpsc::Sender<FullNodeCommand>, id: &str) -> Result<Result<User>> {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    tx.send(FullNodeCommand::GetUser { id: id.to_string(), reply: reply_tx })
+        .await
+        .map_err(|_| anyhow!("Node channel closed"))?;
+    Ok(reply_rx.await?.map_err(|e| anyhow!("{e}")))
+}
+
 // ---------------------------------------------------------------------------
 // Connections
 // ---------------------------------------------------------------------------
 
-async fn cmd_connection(app: &mut App, rest: &str) -> Result<()> {
+async fn cmd_connection(app: &mut App, rest: &str, dry_run: bool) -> Result<()> {
     let arg = rest.trim();
     if arg.is_empty() {
         show_lines(app, "Connection", vec!["Usage: /connection <nick>".to_string()]);
         return Ok(());
     }
 
-    let to_id = match resolve_nick(arg) {
+    let to_id = match resolve_nick(app, arg) {
         Some(id) => id,
         None => {
             show_lines(app, "Connection", vec![format!(
@@ -481,6 +2689,26 @@ async fn cmd_connection(app: &mut App, rest: &str) -> Result<()> {
         }
     };
 
+    if dry_run {
+        let existing = app.connections.iter().find(|c| c.to_id == to_id).map(|c| c.is_established());
+        let state = match existing {
+            Some(true) => "established",
+            Some(false) => "pending",
+            None => "none",
+        };
+        show_dry_run(app, "Connection", vec![
+            format!("Would initiate a connection with {}.", arg),
+            String::new(),
+            format!("  to         : {}", to_id),
+            format!("  existing   : {}", state),
+            "  result     : a pending connection until they run /acceptConnection".to_string(),
+        ]);
+        return Ok(());
+    }
+    if refuse_if_read_only(app, "Connection") {
+        return Ok(());
+    }
+
     let tx = match &app.node_tx {
         Some(tx) => tx.clone(),
         None => {
@@ -498,19 +2726,19 @@ async fn cmd_connection(app: &mut App, rest: &str) -> Result<()> {
         Ok(conn) => {
             let state = if conn.is_established() { "established" } else { "pending" };
             app.push_event(format!("[CONN] → {} [{}]", truncate_id(&conn.to_id, 16), state));
+            if let Err(e) = app.trust.uncancel(&conn.to_id) {
+                app.push_event(format!("[CONN] Earlier cancellation not cleared: {e}"));
+            }
             app.push_output(format!("Connection initiated with {} [{}].", arg, state));
-            let lines = vec![
-                format!("Connection initiated  [{}]", state),
-                String::new(),
-                format!("  from  : {}", conn.from_id),
-                format!("  to    : {}", conn.to_id),
-                format!("  state : {}", state),
-            ];
+            show_connection(app, "Connection", &format!("Connection initiated  [{}]", state), &conn);
+            if !conn.is_established() {
+                wait_for_accept(app, &conn, arg);
+            }
             if !app.connections.iter().any(|c| c.to_id == conn.to_id) {
                 app.connections.push(conn);
             }
-            app.set_content("Connection", lines);
         }
+        Err(e) if storage_write_failed(app, "Connection", &e) => {}
         Err(e) => {
             app.push_event(format!("[CONN] Create failed: {e}"));
             show_lines(app, "Connection", vec![format!("Error creating connection: {e}")]);
@@ -520,18 +2748,119 @@ async fn cmd_connection(app: &mut App, rest: &str) -> Result<()> {
     Ok(())
 }
 
-fn cmd_connections(app: &mut App) -> Result<()> {
-    let local_user = load_local_user(None);
-    let from_id = local_user.as_ref().map(|u| u.id.clone()).unwrap_or_default();
+/// Watch the pending `conn` shown in the view for `CONNECT_WAIT`, telling
+/// the reader so.
+fn wait_for_accept(app: &mut App, conn: &Connection, nick: &str) {
+    app.connect_wait = Some(ConnectWait {
+        from_id: conn.from_id.clone(),
+        to_id: conn.to_id.clone(),
+        nick: nick.to_string(),
+        since: Instant::now(),
+    });
+    app.content_lines.push(String::new());
+    app.content_lines.push(format!(
+        "  Waiting up to {}s for {} to accept; this view updates when they do.",
+        CONNECT_WAIT.as_secs(),
+        nick
+    ));
+}
+
+/// Re-issue the connection request for a peer we already have a connection
+/// record with, to refresh it after they were gone for a while.
+async fn cmd_reconnect(app: &mut App, rest: &str) -> Result<()> {
+    let arg = rest.trim();
+    if arg.is_empty() {
+        show_lines(app, "Reconnect", vec!["Usage: /reconnect <nick>".to_string()]);
+        return Ok(());
+    }
+    let Some(peer_id) = resolve_nick(app, arg) else {
+        show_lines(app, "Reconnect", vec![format!("No user found with nick '{}'. Use /users to see known users.", arg)]);
+        return Ok(());
+    };
+    let local_id = local_id(app).unwrap_or_default();
+    let existing = load_connections(app)
+        .into_iter()
+        .find(|c| Direction::of(c, &local_id).peer(c) == peer_id);
+    let Some(existing) = existing else {
+        show_lines(app, "Reconnect", vec![format!("No connection with {} on record. Use /connection {} to start one.", arg, arg)]);
+        return Ok(());
+    };
+    if refuse_if_read_only(app, "Reconnect") {
+        return Ok(());
+    }
+    let Some(tx) = app.node_tx.clone() else {
+        show_lines(app, "Reconnect", vec!["Node is not running. Use /startNode first.".to_string()]);
+        return Ok(());
+    };
+
+    let (reply_tx, reply_rx) = oneshot::channel();
+    tx.send(FullNodeCommand::CreateConnection { to_id: peer_id.clone(), reply: reply_tx })
+        .await
+        .map_err(|_| anyhow!("Node channel closed"))?;
 
-    let to_ids = list_connections(None).unwrap_or_default();
-    let mut conns: Vec<Connection> = Vec::new();
-    for to_id in &to_ids {
-        if let Ok(c) = load_connection(&from_id, to_id, None) {
-            conns.push(c);
+    match reply_rx.await? {
+        Ok(conn) if conn.is_established() => {
+            app.push_event(format!("[CONN] 🔒 Reconnect {}: already established", truncate_id(&peer_id, 16)));
+            app.push_output(format!("Connection with {} is already established.", arg));
+            show_connection(app, "Reconnect", "Connection  [already established]", &conn);
+            replace_connection(app, &existing, conn);
+        }
+        Ok(conn) => {
+            app.push_event(format!("[CONN] 🔓 Reconnect {}: re-initiated, pending", truncate_id(&peer_id, 16)));
+            if let Err(e) = app.trust.uncancel(&peer_id) {
+                app.push_event(format!("[CONN] Earlier cancellation not cleared: {e}"));
+            }
+            app.push_output(format!("Connection with {} re-initiated [pending].", arg));
+            show_connection(app, "Reconnect", "Connection re-initiated  [pending]", &conn);
+            wait_for_accept(app, &conn, arg);
+            replace_connection(app, &existing, conn);
+        }
+        Err(e) if storage_write_failed(app, "Reconnect", &e) => {}
+        Err(e) => {
+            app.push_event(format!("[CONN] Reconnect failed: {e}"));
+            show_lines(app, "Reconnect", vec![format!("Error re-initiating connection: {e}")]);
         }
     }
+    Ok(())
+}
+
+/// Swap `old` for `new` in the cached connections.
+fn replace_connection(app: &mut App, old: &Connection, new: Connection) {
+    let same = |a: &Connection, b: &Connection| a.from_id == b.from_id && a.to_id == b.to_id;
+    app.connections.retain(|c| !same(c, old) && !same(c, &new));
+    app.connections.push(new);
+}
+
+/// How long `/connection` watches a new request before reporting that it is
+/// still pending.
+const CONNECT_WAIT: Duration = Duration::from_secs(10);
+
+/// Report a connection `/connection` initiated that was not established
+/// within `CONNECT_WAIT`. Called on every tick of the main loop; the node
+/// event handler clears the wait when the connection does establish.
+pub fn check_connect_wait(app: &mut App) {
+    if app.connect_wait.as_ref().is_none_or(|w| w.since.elapsed() < CONNECT_WAIT) {
+        return;
+    }
+    let Some(wait) = app.connect_wait.take() else {
+        return;
+    };
+    let msg = format!(
+        "{} has not accepted after {}s — the request stays pending until they run /acceptConnection.",
+        wait.nick,
+        CONNECT_WAIT.as_secs()
+    );
+    app.push_event(format!("[CONN] 🔓 {}", msg));
+    app.push_output(msg.clone());
+    if app.connection_view.as_ref() == Some(&(wait.from_id, wait.to_id)) {
+        app.content_lines.push(format!("  ⚠ {}", msg));
+    }
+}
+
+fn cmd_connections(app: &mut App) -> Result<()> {
+    let conns = load_connections(app);
     app.connections = conns.clone();
+    let local_id = local_id(app).unwrap_or_default();
 
     let mut lines = vec![format!("Connections  ({})", conns.len()), String::new()];
     if conns.is_empty() {
@@ -539,41 +2868,148 @@ fn cmd_connections(app: &mut App) -> Result<()> {
     } else {
         for c in &conns {
             let state = if c.is_established() { "established" } else { "pending   " };
-            lines.push(format!("  [{}]  {} → {}", state, truncate_id(&c.from_id, 16), truncate_id(&c.to_id, 16)));
+            let dir = Direction::of(c, &local_id);
+            let verified = if app.trust.is_verified(dir.peer(c)) { "  ✓" } else { "" };
+            lines.push(format!(
+                "  {} [{}]  {}  {} → {}{}",
+                lock_icon(c.is_established()),
+                state,
+                dir.label(),
+                party_label(app, &c.from_id, &local_id),
+                party_label(app, &c.to_id, &local_id),
+                verified
+            ));
         }
     }
+    push_storage_notice(app, &mut lines, "connection");
     app.push_output(format!("Connections: {}.", conns.len()));
     app.set_content("Connections", lines);
     Ok(())
 }
 
 fn cmd_connections_pending(app: &mut App) -> Result<()> {
-    let local_user = load_local_user(None);
-    let from_id = local_user.as_ref().map(|u| u.id.clone()).unwrap_or_default();
-
-    let to_ids = list_connections(None).unwrap_or_default();
-    let pending: Vec<Connection> = to_ids
-        .iter()
-        .filter_map(|to_id| load_connection(&from_id, to_id, None).ok())
+    let pending: Vec<Connection> = load_connections(app)
+        .into_iter()
         .filter(|c| !c.is_established())
         .collect();
+    let local_id = local_id(app).unwrap_or_default();
 
     let mut lines = vec![format!("Pending connections  ({})", pending.len()), String::new()];
     if pending.is_empty() {
         lines.push("  No pending connections.".to_string());
     } else {
         for c in &pending {
-            lines.push(format!("  {} → {}", truncate_id(&c.from_id, 16), truncate_id(&c.to_id, 16)));
+            let dir = Direction::of(c, &local_id);
+            lines.push(format!(
+                "  {}  {} → {}",
+                dir.label(),
+                party_label(app, &c.from_id, &local_id),
+                party_label(app, &c.to_id, &local_id)
+            ));
+            match dir {
+                Direction::Outbound => lines.push("    waiting for them to accept".to_string()),
+                Direction::Inbound => lines.push(format!(
+                    "    accept with /acceptConnection {} <their_public_key>",
+                    c.from_id
+                )),
+            }
             if let Some(pub_k) = &c.public_key {
                 lines.push(format!("    our_public_key: {}", pub_k));
             }
         }
     }
-    app.set_content("Connections (Pending)", lines);
-    Ok(())
+    push_storage_notice(app, &mut lines, "connection");
+    app.set_content("Connections (Pending)", lines);
+    Ok(())
+}
+
+fn cmd_connections_established(app: &mut App) -> Result<()> {
+    let established: Vec<Connection> = load_connections(app)
+        .into_iter()
+        .filter(|c| c.is_established())
+        .collect();
+    let local_id = local_id(app).unwrap_or_default();
+
+    let mut lines = vec![format!("Established connections  ({})", established.len()), String::new()];
+    if established.is_empty() {
+        lines.push("  No established connections. See /connectionsPending.".to_string());
+    } else {
+        for c in &established {
+            let dir = Direction::of(c, &local_id);
+            let peer = dir.peer(c);
+            let name = known_name(app, peer).map(|name| format!("  {}", name)).unwrap_or_default();
+            let verified = if app.trust.is_verified(peer) { "  ✓" } else { "" };
+            lines.push(format!(
+                "  {} {}  {}{}{}",
+                lock_icon(true),
+                dir.label(),
+                id_label(app, peer, 16),
+                name,
+                verified
+            ));
+        }
+    }
+    push_storage_notice(app, &mut lines, "connection");
+    app.set_content("Connections (Established)", lines);
+    Ok(())
+}
+
+/// The display name stored for a known user, if any.
+fn known_name(app: &App, id: &str) -> Option<String> {
+    load_known_user(id, app.storage()).ok().and_then(|meta| meta.display_name)
+}
+
+/// One side of a connection for a listing: "you" for the local user,
+/// otherwise the display name with the id, or just the id.
+fn party_label(app: &App, id: &str, local_id: &str) -> String {
+    if id == local_id {
+        return "you".to_string();
+    }
+    match known_name(app, id) {
+        Some(name) => format!("{} ({})", name, id_label(app, id, 16)),
+        None => id_label(app, id, 16),
+    }
+}
+
+/// Who initiated a connection, from the local user's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    /// We are `from`: we initiated it.
+    Outbound,
+    /// We are `to`: the remote initiated it.
+    Inbound,
+}
+
+impl Direction {
+    fn of(conn: &Connection, local_id: &str) -> Self {
+        if conn.to_id == local_id { Direction::Inbound } else { Direction::Outbound }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Direction::Outbound => "↑ outbound",
+            Direction::Inbound => "↓ inbound ",
+        }
+    }
+
+    /// The remote side of the connection.
+    fn peer(self, conn: &Connection) -> &str {
+        match self {
+            Direction::Outbound => &conn.to_id,
+            Direction::Inbound => &conn.from_id,
+        }
+    }
+}
+
+/// The local user's id, loaded once and cached on the app.
+pub fn local_id(app: &mut App) -> Option<String> {
+    if app.local_id.is_none() {
+        app.local_id = load_local_user(app.storage()).ok().map(|u| u.id);
+    }
+    app.local_id.clone()
 }
 
-async fn cmd_accept_connection(app: &mut App, rest: &str) -> Result<()> {
+async fn cmd_accept_connection(app: &mut App, rest: &str, dry_run: bool) -> Result<()> {
     let parts: Vec<&str> = rest.splitn(2, ' ').collect();
     if parts.len() < 2 {
         show_lines(app, "Accept Connection", vec!["Usage: /acceptConnection <from_id> <their_public_key>".to_string()]);
@@ -582,6 +3018,21 @@ async fn cmd_accept_connection(app: &mut App, rest: &str) -> Result<()> {
     let from_id = parts[0].trim();
     let their_pub_key = parts[1].trim();
 
+    if dry_run {
+        show_dry_run(app, "Accept Connection", vec![
+            format!("Would accept the connection request from {}.", id_label(app, from_id, 16)),
+            String::new(),
+            format!("  from        : {}", from_id),
+            format!("  their key   : {}", their_pub_key),
+            format!("  fingerprint : {}", trust::fingerprint(their_pub_key)),
+            "  result      : DH key exchange, connection established".to_string(),
+        ]);
+        return Ok(());
+    }
+    if refuse_if_read_only(app, "Accept Connection") {
+        return Ok(());
+    }
+
     let tx = match &app.node_tx {
         Some(tx) => tx.clone(),
         None => {
@@ -590,32 +3041,21 @@ async fn cmd_accept_connection(app: &mut App, rest: &str) -> Result<()> {
         }
     };
 
-    let (reply_tx, reply_rx) = oneshot::channel();
-    tx.send(FullNodeCommand::AcceptConnection {
-        from_id: from_id.to_string(),
-        their_public_key: their_pub_key.to_string(),
-        reply: reply_tx,
-    })
-    .await
-    .map_err(|_| anyhow!("Node channel closed"))?;
-
-    match reply_rx.await? {
+    match request_accept(&tx, from_id, their_pub_key).await? {
         Ok(conn) => {
             app.push_event(format!("[CONN] Accepted from {} — DH key established.", truncate_id(&conn.from_id, 16)));
+            if let Err(e) = app.trust.undecline(&conn.from_id) {
+                app.push_event(format!("[CONN] Earlier decline not cleared: {e}"));
+            }
             app.push_output(format!("Connection with {} accepted.", conn.from_id));
-            let lines = vec![
-                format!("Connection accepted  [established]"),
-                String::new(),
-                format!("  from  : {}", conn.from_id),
-                format!("  to    : {}", conn.to_id),
-            ];
+            show_connection(app, "Accept Connection", "Connection accepted  [established]", &conn);
             let idx = app.connections.iter().position(|c| c.from_id == conn.from_id);
             match idx {
                 Some(i) => app.connections[i] = conn,
                 None => app.connections.push(conn),
             }
-            app.set_content("Accept Connection", lines);
         }
+        Err(e) if storage_write_failed(app, "Accept Connection", &e) => {}
         Err(e) => {
             app.push_event(format!("[CONN] Accept failed: {e}"));
             show_lines(app, "Accept Connection", vec![format!("Error accepting connection: {e}")]);
@@ -625,26 +3065,281 @@ async fn cmd_accept_connection(app: &mut App, rest: &str) -> Result<()> {
     Ok(())
 }
 
-fn cmd_decline_connection(app: &mut App, rest: &str) {
+/// Ask the node to accept a connection request. The outer error means the
+/// node is unreachable, the inner one that the node refused.
+async fn request_accept(tx: &mpsc::Sender<FullNodeCommand>, from_id: &str, their_pub_key: &str) -> Result<Result<Connection>> {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    tx.send(FullNodeCommand::AcceptConnection {
+        from_id: from_id.to_string(),
+        their_public_key: their_pub_key.to_string(),
+        reply: reply_tx,
+    })
+    .await
+    .map_err(|_| anyhow!("Node channel closed"))?;
+    Ok(reply_rx.await?.map_err(|e| anyhow!("{e}")))
+}
+
+/// Handle a newly seen incoming connection request: accept it right away if
+/// the requester matches the auto-accept policy, otherwise tell the user how
+/// to accept it by hand.
+pub async fn offer_connection(app: &mut App, conn: &Connection) {
+    let from = truncate_id(&conn.from_id, 16);
+    let allowed = match app.auto_accept {
+        AutoAccept::Off => false,
+        AutoAccept::Contacts => load_known_user(&conn.from_id, app.storage()).is_ok(),
+        AutoAccept::Verified => app.trust.is_verified(&conn.from_id),
+        AutoAccept::All => true,
+    };
+    let (tx, their_pub_key) = match (&app.node_tx, &conn.public_key) {
+        (Some(tx), Some(key)) if allowed => (tx.clone(), key.clone()),
+        (_, Some(_)) => {
+            app.push_event(format!(
+                "[CONN] Connection request from {} — accept with /acceptConnection {} <their_public_key>.",
+                from, conn.from_id
+            ));
+            return;
+        }
+        (_, None) => {
+            app.push_event(format!("[CONN] Connection request from {} carries no public key.", from));
+            return;
+        }
+    };
+
+    match request_accept(&tx, &conn.from_id, &their_pub_key).await {
+        Ok(Ok(accepted)) => {
+            app.push_event(format!(
+                "[CONN] Auto-accepted request from {} (policy: {}).",
+                from,
+                app.auto_accept.name()
+            ));
+            // The watcher reports the established state on its next pass.
+            app.push_output(format!("Connection with {} accepted automatically.", accepted.from_id));
+        }
+        Ok(Err(e)) => {
+            app.push_event(format!("[CONN] Auto-accept of {} failed: {e}", from));
+        }
+        Err(e) => {
+            app.push_event(format!("[CONN] Auto-accept of {} failed: {e}", from));
+            node_died(app);
+        }
+    }
+}
+
+/// Load every stored connection of the local user. Records that fail to load
+/// are kept in `app.storage_issues` instead of silently vanishing. Established
+/// connections come first, then pending ones, each by the peer's name.
+fn load_connections(app: &mut App) -> Vec<Connection> {
+    let local_user = load_local_user(app.storage());
+    let from_id = local_user.as_ref().map(|u| u.id.clone()).unwrap_or_default();
+
+    let mut conns = Vec::new();
+    let mut issues = Vec::new();
+    for to_id in list_connections(app.storage()).unwrap_or_default() {
+        match load_connection(&from_id, &to_id, app.storage()) {
+            // Requests the user declined or cancelled stay out of every listing.
+            Ok(c) if is_dropped_request(app, &c, &from_id) => {}
+            Ok(c) => conns.push(c),
+            Err(e) => issues.push(StorageIssue { record: "connection", id: to_id, error: e.to_string() }),
+        }
+    }
+    app.set_storage_issues("connection", issues);
+    conns.sort_by_cached_key(|c| {
+        let peer = Direction::of(c, &from_id).peer(c).to_string();
+        let name = known_name(app, &peer).map(|name| name.to_lowercase());
+        // Unnamed peers sort after named ones, by id.
+        (!c.is_established(), name.is_none(), name, peer)
+    });
+    conns
+}
+
+/// Whether `conn` is a pending request from someone the user declined, or
+/// one of ours the user cancelled.
+pub fn is_dropped_request(app: &App, conn: &Connection, local_id: &str) -> bool {
+    !conn.is_established()
+        && ((conn.to_id == local_id && app.trust.is_declined(&conn.from_id))
+            || (conn.from_id == local_id && app.trust.is_cancelled(&conn.to_id)))
+}
+
+/// Render the detail view for one connection, remembering which connection
+/// it is so state-change events can refresh it in place.
+pub fn show_connection(app: &mut App, title: &str, heading: &str, conn: &Connection) {
+    let state = if conn.is_established() { "established" } else { "pending" };
+    let local_id = local_id(app).unwrap_or_default();
+    let dir = Direction::of(conn, &local_id);
+    let peer_id = dir.peer(conn);
+    let name = |id: &str| {
+        if id == local_id {
+            return "  (you)".to_string();
+        }
+        known_name(app, id).map(|name| format!("  ({})", name)).unwrap_or_default()
+    };
+    let times = app.connection_times.get(&(conn.from_id.clone(), conn.to_id.clone())).copied().unwrap_or_default();
+    let seen = |at: Option<SystemTime>| match at {
+        Some(at) => TimeDisplay::Absolute.label(at).unwrap_or_default(),
+        None => "not seen this session".to_string(),
+    };
+    let mut lines = vec![
+        heading.to_string(),
+        String::new(),
+        format!("  from       : {}{}", conn.from_id, name(&conn.from_id)),
+        format!("  to         : {}{}", conn.to_id, name(&conn.to_id)),
+        format!("  direction  : {}", dir.label().trim_end()),
+        format!("  state      : {}", state),
+        format!("  encryption : {}", encryption_label(conn.is_established())),
+        format!("  verified   : {}", if app.trust.is_verified(peer_id) { "✓ yes" } else { "no (see /fingerprint)" }),
+        format!("  public key : {}", conn.public_key.as_deref().unwrap_or("(none)")),
+        format!("  created    : {}", seen(times.created)),
+    ];
+    if conn.is_established() {
+        lines.push(format!("  accepted   : {}", seen(times.accepted)));
+    }
+    app.set_content(title, lines);
+    app.connection_view = Some((conn.from_id.clone(), conn.to_id.clone()));
+}
+
+/// Everything about one connection, e.g. to read its ids and key out to the
+/// peer while they run `/acceptConnection`.
+fn cmd_connection_info(app: &mut App, rest: &str) {
+    let arg = rest.trim();
+    if arg.is_empty() {
+        show_lines(app, "Connection Info", vec!["Usage: /connectionInfo <nick>".to_string()]);
+        return;
+    }
+    let peer_id = resolve_nick(app, arg).unwrap_or_else(|| arg.to_string());
+    let local_id = local_id(app).unwrap_or_default();
+    let conns = load_connections(app);
+    match conns.into_iter().find(|c| Direction::of(c, &local_id).peer(c) == peer_id) {
+        Some(conn) => {
+            let state = if conn.is_established() { "established" } else { "pending" };
+            show_connection(app, "Connection Info", &format!("Connection  [{}]", state), &conn);
+        }
+        None => app.set_error(
+            "Connection Info",
+            vec![format!("No connection with '{}' on record. See /connections.", arg)],
+        ),
+    }
+}
+
+fn lock_icon(established: bool) -> &'static str {
+    if established { "🔒" } else { "🔓" }
+}
+
+fn encryption_label(established: bool) -> String {
+    if established {
+        format!("{} end-to-end encrypted (key exchanged)", lock_icon(true))
+    } else {
+        format!("{} NOT encrypted yet — key exchange pending", lock_icon(false))
+    }
+}
+
+/// Decline someone else's pending request. Our own outbound requests are
+/// withdrawn with `/cancelConnection` instead.
+fn cmd_decline_connection(app: &mut App, rest: &str, dry_run: bool) {
     let user_id = rest.trim();
     if user_id.is_empty() {
         show_lines(app, "Decline Connection", vec!["Usage: /declineConnection <connection_id>".to_string()]);
         return;
     }
-    app.connections.retain(|c| c.to_id != user_id && c.from_id != user_id);
-    app.push_event(format!("[CONN] Declined connection with {}.", truncate_id(user_id, 16)));
-    show_lines(app, "Decline Connection", vec![
-        format!("Connection with {} removed locally.", user_id),
-        "(Network-level decline not yet implemented in the library.)".to_string(),
-    ]);
+    if app.connections.is_empty() {
+        app.connections = load_connections(app);
+    }
+    let local_id = local_id(app).unwrap_or_default();
+    let is_request = |c: &Connection| {
+        !c.is_established() && Direction::of(c, &local_id) == Direction::Inbound && c.from_id == user_id
+    };
+    if !app.connections.iter().any(is_request) {
+        let ours = app
+            .connections
+            .iter()
+            .any(|c| Direction::of(c, &local_id) == Direction::Outbound && c.to_id == user_id);
+        let msg = if ours {
+            format!("{} has no request to decline — the connection is your own. Use /cancelConnection <nick> to withdraw it.", user_id)
+        } else {
+            format!("No pending request from {}. See /connectionsPending.", user_id)
+        };
+        show_lines(app, "Decline Connection", vec![msg]);
+        return;
+    }
+    if dry_run {
+        show_dry_run(app, "Decline Connection", vec![
+            format!("Would decline the connection request from {} and hide it from now on.", user_id),
+        ]);
+        return;
+    }
+    app.connections.retain(|c| !is_request(c));
+    app.push_event(format!("[CONN] Declined request from {}.", truncate_id(user_id, 16)));
+    let mut lines = vec![format!("Request from {} declined; it stays hidden from now on.", user_id)];
+    if let Err(e) = app.trust.decline(user_id) {
+        app.push_event(format!("[CONN] Decline not saved: {e}"));
+        lines.push(format!("  ⚠ Could not save the decline ({e}), so the request returns after a restart."));
+    }
+    lines.push("(Telling the peer, and deleting the stored request, is not yet implemented in the library.)".to_string());
+    show_lines(app, "Decline Connection", lines);
+}
+
+/// Withdraw a connection request we sent that hasn't been accepted yet.
+fn cmd_cancel_connection(app: &mut App, rest: &str, dry_run: bool) {
+    let nick = rest.trim();
+    if nick.is_empty() {
+        show_lines(app, "Cancel Connection", vec!["Usage: /cancelConnection <nick>".to_string()]);
+        return;
+    }
+    let Some(peer_id) = resolve_nick(app, nick) else {
+        show_lines(app, "Cancel Connection", vec![format!(
+            "No user found with nick '{}'. Use /users to see known users.", nick
+        )]);
+        return;
+    };
+
+    if app.connections.is_empty() {
+        app.connections = load_connections(app);
+    }
+    let local_id = local_id(app).unwrap_or_default();
+    let ours = |c: &Connection| Direction::of(c, &local_id) == Direction::Outbound && c.to_id == peer_id;
+    let msg = match app.connections.iter().find(|c| ours(c)) {
+        Some(c) if c.is_established() => Some(format!(
+            "Your connection with {} is already established; there is no pending request to cancel.", nick
+        )),
+        Some(_) => None,
+        None if app.connections.iter().any(|c| c.from_id == peer_id) => Some(format!(
+            "The pending connection with {} is their request, not yours. Use /declineConnection {} to decline it.",
+            nick, peer_id
+        )),
+        None => Some(format!("You have no pending connection request to {}. See /connectionsPending.", nick)),
+    };
+    if let Some(msg) = msg {
+        show_lines(app, "Cancel Connection", vec![msg]);
+        return;
+    }
+    if dry_run {
+        show_dry_run(app, "Cancel Connection", vec![
+            format!("Would withdraw your pending connection request to {} and hide it from now on.", nick),
+        ]);
+        return;
+    }
+
+    app.connections.retain(|c| !ours(c));
+    app.push_event(format!("[CONN] Cancelled request to {}.", nick));
+    app.push_output(format!("Connection request to {} cancelled.", nick));
+    let mut lines = vec![format!(
+        "Your pending connection request to {} was cancelled; it stays hidden from now on.", nick
+    )];
+    if let Err(e) = app.trust.cancel(&peer_id) {
+        app.push_event(format!("[CONN] Cancellation not saved: {e}"));
+        lines.push(format!("  ⚠ Could not save the cancellation ({e}), so the request returns after a restart."));
+    }
+    lines.push(
+        "(Aborting the handshake, and deleting the stored request, is not yet implemented in the library.)".to_string(),
+    );
+    show_lines(app, "Cancel Connection", lines);
 }
 
 // ---------------------------------------------------------------------------
 // Messages
 // ---------------------------------------------------------------------------
 
-async fn cmd_message(app: &mut App, rest: &str) -> Result<()> {
-    let parts: Vec<&str> = rest.splitn(2, ' ').collect();
+async fn cmd_message(app: &mut App, rest: &str, dry_run: bool) -> Result<()> {
+    let parts: Vec<&str> = rest.splitn(2, char::is_whitespace).collect();
     if parts.len() < 2 {
         show_lines(app, "Message", vec!["Usage: /message <nick> <body>".to_string()]);
         return Ok(());
@@ -652,7 +3347,7 @@ async fn cmd_message(app: &mut App, rest: &str) -> Result<()> {
     let nick = parts[0].trim();
     let body = parts[1].trim();
 
-    let to_id = match resolve_nick(nick) {
+    let to_id = match resolve_nick(app, nick) {
         Some(id) => id,
         None => {
             show_lines(app, "Message", vec![format!(
@@ -662,10 +3357,10 @@ async fn cmd_message(app: &mut App, rest: &str) -> Result<()> {
         }
     };
 
-    send_message(app, nick, &to_id, "text", serde_json::json!({ "text": body })).await
+    send_message(app, nick, &to_id, "text", serde_json::json!({ "text": body }), dry_run).await
 }
 
-async fn cmd_message_plugin(app: &mut App, rest: &str) -> Result<()> {
+async fn cmd_message_plugin(app: &mut App, rest: &str, dry_run: bool) -> Result<()> {
     let parts: Vec<&str> = rest.splitn(3, ' ').collect();
     if parts.len() < 3 {
         show_lines(app, "Message", vec!["Usage: /messagePlugin <nick> <plugin_type> <plugin_body>".to_string()]);
@@ -675,7 +3370,7 @@ async fn cmd_message_plugin(app: &mut App, rest: &str) -> Result<()> {
     let plugin_type = parts[1].trim();
     let plugin_body_str = parts[2].trim();
 
-    let to_id = match resolve_nick(nick) {
+    let to_id = match resolve_nick(app, nick) {
         Some(id) => id,
         None => {
             show_lines(app, "Message", vec![format!(
@@ -685,10 +3380,104 @@ async fn cmd_message_plugin(app: &mut App, rest: &str) -> Result<()> {
         }
     };
 
-    let plugin_body = serde_json::from_str(plugin_body_str)
-        .unwrap_or_else(|_| serde_json::json!({ "raw": plugin_body_str }));
+    let mut notes = Vec::new();
+    let plugin_body = serde_json::from_str(plugin_body_str).unwrap_or_else(|_| {
+        notes.push("The body is not valid JSON, so it went as a string under \"raw\".".to_string());
+        serde_json::json!({ "raw": plugin_body_str })
+    });
+    if !PLUGIN_TYPES.iter().any(|(name, _, _)| *name == plugin_type) {
+        notes.push(format!("'{}' is not a known plugin type (see /plugins); peers may not interpret it.", plugin_type));
+    }
+
+    send_message(app, nick, &to_id, plugin_type, plugin_body, dry_run).await?;
+    if !notes.is_empty() && app.content_kind != ViewKind::Error {
+        app.content_lines.push(String::new());
+        for note in notes {
+            app.push_output(note.clone());
+            app.content_lines.push(format!("  Note: {}", note));
+        }
+    }
+    Ok(())
+}
+
+/// Plugin types with an agreed meaning: name, body format and description.
+/// The library has no registry of its own, so this is the reference.
+const PLUGIN_TYPES: &[(&str, &str, &str)] = &[
+    (
+        "text",
+        r#"{"text": "<body>", "in_reply_to": "<hash>"?, "sent_at": <unix secs>?}"#,
+        "Plain text message, as sent by /message and /reply",
+    ),
+    ("ping", r#"{"nonce": "<hex>"}"#, "Liveness check sent by /ping; answered automatically with a pong"),
+    ("pong", r#"{"nonce": "<hex>"}"#, "Answer to a ping, echoing its nonce"),
+    ("receipt", r#"{"hash": "<hash>"}"#, "Delivery confirmation, sent automatically for each message received"),
+];
+
+fn cmd_plugins(app: &mut App) {
+    let mut lines = vec![format!("Plugin types  ({})", PLUGIN_TYPES.len()), String::new()];
+    for (name, body, about) in PLUGIN_TYPES {
+        lines.push(format!("  {:<10}{}", name, about));
+        lines.push(format!("  {:<10}body: {}", "", body));
+    }
+    lines.push(String::new());
+    lines.push("Send one with /messagePlugin <nick> <type> <json body>. A body that is not JSON is sent as {\"raw\": …}.".to_string());
+    lines.push("Other types are delivered as-is, but peers' clients may not know what to do with them.".to_string());
+    app.push_event("[CMD] /plugins");
+    app.set_content("Plugins", lines);
+}
+
+async fn cmd_resend(app: &mut App, rest: &str, dry_run: bool) -> Result<()> {
+    let prefix = rest.trim();
+    if prefix.is_empty() {
+        show_lines(app, "Message", vec!["Usage: /resend <hash>".to_string()]);
+        return Ok(());
+    }
+
+    let matches: Vec<&MessageEntry> = app
+        .messages
+        .iter()
+        .filter(|m| m.delivery != Delivery::Received && m.hash.starts_with(prefix))
+        .collect();
+    let original = match matches.as_slice() {
+        [m] => (*m).clone(),
+        [] => {
+            show_lines(app, "Message", vec![format!("No sent message with hash '{}'. See /messages.", prefix)]);
+            return Ok(());
+        }
+        _ => {
+            show_lines(app, "Message", vec![format!("Hash '{}' is ambiguous — type more characters.", prefix)]);
+            return Ok(());
+        }
+    };
+
+    if !dry_run {
+        app.push_event(format!("[MSG] Resending {} to {}.", truncate_id(&original.hash, 12), original.nick));
+    }
+    send_message(app, &original.nick, &original.to_id, &original.plugin_type, original.body, dry_run).await
+}
+
+async fn cmd_reply(app: &mut App, rest: &str, dry_run: bool) -> Result<()> {
+    let parts: Vec<&str> = rest.splitn(2, char::is_whitespace).collect();
+    if parts.len() < 2 || parts[1].trim().is_empty() {
+        show_lines(app, "Message", vec!["Usage: /reply <hash> <body>".to_string()]);
+        return Ok(());
+    }
+    let prefix = parts[0].trim();
+    let body = parts[1].trim();
+
+    let original = match app.messages.iter().find(|m| m.hash.starts_with(prefix)) {
+        Some(m) => m.clone(),
+        None => {
+            show_lines(app, "Message", vec![
+                format!("Message '{}' is not known locally, so its sender can't be determined.", prefix),
+                "Use /messages to find the hash of a message in this session.".to_string(),
+            ]);
+            return Ok(());
+        }
+    };
 
-    send_message(app, nick, &to_id, plugin_type, plugin_body).await
+    let plugin_body = serde_json::json!({ "text": body, "in_reply_to": original.hash });
+    send_message(app, &original.nick, original.peer_id(), "text", plugin_body, dry_run).await
 }
 
 async fn send_message(
@@ -697,7 +3486,29 @@ async fn send_message(
     to_id: &str,
     plugin_type: &str,
     plugin_body: serde_json::Value,
+    dry_run: bool,
 ) -> Result<()> {
+    if dry_run {
+        let encrypted = load_local_user(app.storage())
+            .and_then(|u| load_connection(&u.id, to_id, app.storage()))
+            .is_ok_and(|c| c.is_established());
+        let mut lines = vec![
+            format!("Would send a message  [{}]", plugin_type),
+            String::new(),
+            format!("  to         : {} ({})", nick, id_label(app, to_id, 16)),
+        ];
+        lines.extend(body_lines(&plugin_body));
+        lines.push(format!("  encryption : {}", encryption_label(encrypted)));
+        if !encrypted {
+            lines.push(format!("  Warning: no established connection with {} — it may not be delivered.", nick));
+        }
+        show_dry_run(app, "Message", lines);
+        return Ok(());
+    }
+    if refuse_if_read_only(app, "Message") {
+        return Ok(());
+    }
+
     let tx = match &app.node_tx {
         Some(tx) => tx.clone(),
         None => {
@@ -706,10 +3517,13 @@ async fn send_message(
         }
     };
 
-    let local_user = load_local_user(None)
+    let local_user = load_local_user(app.storage())
         .map_err(|_| anyhow!("No local user — run /user first"))?;
+    let connection = load_connection(&local_user.id, to_id, app.storage()).ok();
+    let encrypted = connection.as_ref().is_some_and(|c| c.is_established());
+    let plugin_body = stamp_sent_at(plugin_type, plugin_body);
 
-    let msg = accord_network::Message::new(
+    let msg = Message::new(
         local_user.id.clone(),
         to_id,
         plugin_type,
@@ -717,31 +3531,67 @@ async fn send_message(
     );
     let data = serde_json::to_vec(&msg)?;
 
+    let in_flight = app.send_queue.enter();
+    let depth = app.send_queue.depth();
+    if depth >= SEND_QUEUE_WARN {
+        app.push_event(format!("[MSG] ⚠ {} sends awaiting the node — it is falling behind.", depth));
+    }
+
     let (reply_tx, reply_rx) = oneshot::channel();
     tx.send(FullNodeCommand::StoreMessage { data, reply: reply_tx })
         .await
         .map_err(|_| anyhow!("Node channel closed"))?;
+    let reply = reply_rx.await?;
+    drop(in_flight);
 
-    match reply_rx.await? {
+    match reply {
         Ok(hash) => {
-            let line = format!(
-                "[{}→{}]  [{}]  {}",
-                truncate_id(&local_user.id, 8),
-                truncate_id(to_id, 8),
-                plugin_type,
-                plugin_body
-            );
-            app.messages.push(line.clone());
+            app.activity.record();
+            if let Err(e) = app.message_counts.record(to_id) {
+                app.push_event(format!("[MSG] Message count not saved: {e}"));
+            }
+            app.push_message(MessageEntry {
+                from_id: local_user.id.clone(),
+                to_id: to_id.to_string(),
+                nick: nick.to_string(),
+                plugin_type: plugin_type.to_string(),
+                body: plugin_body.clone(),
+                hash: hash.clone(),
+                sent_at: Instant::now(),
+                at: SystemTime::now(),
+                delivery: Delivery::Pending,
+                skew: None,
+            });
             app.push_event(format!("[MSG] → {} [{}] (hash: {})", nick, plugin_type, truncate_id(&hash, 12)));
             app.push_output(format!("Message sent to {} (hash: {}).", nick, hash));
-            app.set_content("Message", vec![
+            let mut lines = vec![
                 format!("Message sent  [{}]", plugin_type),
                 String::new(),
-                format!("  to   : {} ({})", nick, truncate_id(to_id, 16)),
-                format!("  body : {}", plugin_body),
-                format!("  hash : {}", hash),
+                format!("  to         : {} ({})", nick, id_label(app, to_id, 16)),
+            ];
+            lines.extend(body_lines(&plugin_body));
+            lines.extend([
+                format!("  hash       : {}", hash),
+                format!("  encryption : {}", encryption_label(encrypted)),
+                format!("  delivery   : {} awaiting confirmation", Delivery::Pending.icon()),
             ]);
+            if !encrypted {
+                app.push_event(format!("[MSG] {} Sent to {} without an established connection.", lock_icon(false), nick));
+                let warning = format!(
+                    "No established connection with {} — message stored but may not be delivered, and it is not end-to-end encrypted.",
+                    nick
+                );
+                app.push_output(warning.clone());
+                lines.push(String::new());
+                lines.push(format!("  Warning: {}", warning));
+                lines.push(match connection {
+                    Some(_) => format!("  The connection is pending until {} accepts it.", nick),
+                    None => format!("  Use /connection {} to set one up.", nick),
+                });
+            }
+            app.set_content("Message", lines);
         }
+        Err(e) if storage_write_failed(app, "Message", &e) => {}
         Err(e) => {
             app.push_event(format!("[MSG] Send failed: {e}"));
             show_lines(app, "Message", vec![format!("Error storing message: {e}")]);
@@ -752,43 +3602,426 @@ async fn send_message(
 }
 
 // ---------------------------------------------------------------------------
-// Helpers
+// Stats
+// ---------------------------------------------------------------------------
+
+/// Counters behind `/stats`; also the JSON written by `/status --json`.
+#[derive(Debug, Serialize)]
+struct Stats {
+    node_running: bool,
+    listen_addrs: Vec<String>,
+    peer_id: Option<String>,
+    uptime_secs: Option<u64>,
+    send_queue: usize,
+    messages_sent: usize,
+    messages_pending: usize,
+    messages_unconfirmed: usize,
+    messages_last_minute: u32,
+    peers_stored: usize,
+    peers_live: usize,
+    connections: usize,
+    connections_established: usize,
+    events: usize,
+}
+
+fn stats(app: &App) -> Stats {
+    let listen_addrs = match &app.node_status {
+        NodeStatus::Running { addr } => vec![addr.clone()],
+        NodeStatus::Stopped => Vec::new(),
+    };
+    Stats {
+        node_running: app.node_tx.is_some(),
+        listen_addrs,
+        peer_id: app.local_peer_id.clone(),
+        uptime_secs: app.node_started.map(|t| t.elapsed().as_secs()),
+        send_queue: app.send_queue.depth(),
+        messages_sent: app.messages.len(),
+        messages_pending: app.messages.iter().filter(|m| m.delivery == Delivery::Pending).count(),
+        messages_unconfirmed: app.messages.iter().filter(|m| m.delivery == Delivery::Unconfirmed).count(),
+        messages_last_minute: app.activity.total(),
+        peers_stored: app.peers.len(),
+        peers_live: app.live_peers.len(),
+        connections: app.connections.len(),
+        connections_established: app.connections.iter().filter(|c| c.is_established()).count(),
+        events: app.events.len(),
+    }
+}
+
+fn cmd_stats(app: &mut App) {
+    let s = stats(app);
+    let queue = if s.send_queue >= SEND_QUEUE_WARN {
+        format!("{} in flight  ⚠ node is backed up (≥ {})", s.send_queue, SEND_QUEUE_WARN)
+    } else {
+        format!("{} in flight", s.send_queue)
+    };
+    let node = match s.uptime_secs {
+        Some(secs) => format!("running for {}m {}s", secs / 60, secs % 60),
+        None => "stopped".to_string(),
+    };
+
+    let lines = vec![
+        "Stats".to_string(),
+        String::new(),
+        format!("  node        : {}", node),
+        format!("  send queue  : {}", queue),
+        format!(
+            "  sent        : {}  ({} pending, {} unconfirmed)",
+            s.messages_sent, s.messages_pending, s.messages_unconfirmed
+        ),
+        format!("  last minute : {} messages", s.messages_last_minute),
+        format!("  peers       : {} stored, {} live", s.peers_stored, s.peers_live),
+        format!(
+            "  connections : {}  ({} established, {} pending)",
+            s.connections,
+            s.connections_established,
+            s.connections - s.connections_established
+        ),
+        format!("  events      : {}", s.events),
+    ];
+    app.set_content("Stats", lines);
+}
+
+fn cmd_status(app: &mut App, rest: &str) {
+    match rest.trim() {
+        "" => cmd_stats(app),
+        "--json" => {
+            // One compact line, so monitors can read it from /console or a log.
+            let json = serde_json::to_string(&stats(app)).unwrap_or_else(|e| format!("{{\"error\":\"{e}\"}}"));
+            app.push_output(json.clone());
+            app.set_content("Status", vec![json]);
+        }
+        other => app.set_error("Status", vec![format!("Unknown option '{}'. Usage: /status [--json]", other)]),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Storage diagnostics
 // ---------------------------------------------------------------------------
 
-/// Resolve a display-name (nick) to a user ID (case-insensitive).
-fn resolve_nick(nick: &str) -> Option<String> {
-    if let Ok(local) = load_local_user(None) {
-        if local.meta.display_name.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(nick)) {
-            return Some(local.id);
+fn cmd_storage(app: &mut App, rest: &str) {
+    let recheck = match rest.trim() {
+        "" => false,
+        "recheck" => true,
+        other => {
+            app.set_error("Storage", vec![format!("Unknown option '{}'. Usage: /storage [recheck]", other)]);
+            return;
+        }
+    };
+
+    let path = storage_path_label(app);
+    let writable = probe_storage(app);
+    if recheck && app.read_only.is_some() && !matches!(writable, Some(Err(_))) {
+        app.read_only = None;
+        app.push_event(format!("[STORAGE] {} is writable again — leaving read-only mode.", path));
+    }
+    let mut lines = vec![format!("Storage  {}", path), String::new()];
+    match (&app.read_only, writable) {
+        (Some(reason), _) => {
+            lines.push(format!("  ⚠ read-only mode: {}", reason));
+            lines.push("    Free space or fix permissions, then /storage recheck.".to_string());
+        }
+        (None, Some(Err(e))) => lines.push(format!("  ⚠ not writable: {}", e)),
+        (None, Some(Ok(()))) => lines.push("  writable".to_string()),
+        (None, None) => {
+            lines.push("  not checked — the library's default location can only be tested by saving".to_string());
+            lines.push("  your user record, and there is none yet. Run /user, then /storage again.".to_string());
+        }
+    }
+    lines.push(String::new());
+
+    lines.push(format!("Unreadable records  ({})", app.storage_issues.len()));
+    lines.push(String::new());
+    if app.storage_issues.is_empty() {
+        lines.push("  No read errors on the last load of /users or /connections.".to_string());
+    } else {
+        for issue in &app.storage_issues {
+            lines.push(format!("  [{}]  {}", issue.record, issue.id));
+            lines.push(format!("      {}", issue.error));
         }
+        lines.push(String::new());
+        lines.push("  Use /repair for recovery steps.".to_string());
+    }
+    app.set_content("Storage", lines);
+}
+
+fn cmd_repair(app: &mut App) {
+    if app.storage_issues.is_empty() {
+        show_lines(app, "Repair", vec!["Nothing to repair — run /users or /connections to re-check.".to_string()]);
+        return;
+    }
+    let mut lines = vec![
+        format!("{} unreadable record(s):", app.storage_issues.len()),
+        String::new(),
+    ];
+    for issue in &app.storage_issues {
+        lines.push(format!("  [{}]  {}", issue.record, issue.id));
+    }
+    lines.push(String::new());
+    lines.push("  Move these files out of the node's storage directory to quarantine them,".to_string());
+    lines.push("  then re-run the command; a peer can re-share its record afterwards.".to_string());
+    lines.push("(Automatic quarantine needs a storage API that exposes record paths; the library has none yet.)".to_string());
+    app.push_event(format!("[STORAGE] Repair requested for {} record(s).", app.storage_issues.len()));
+    app.set_content("Repair", lines);
+}
+
+/// Probe the storage location up front and enter read-only mode if it
+/// refuses writes. Run at startup and when the node starts.
+pub fn check_storage(app: &mut App) {
+    if let Some(Err(e)) = probe_storage(app) {
+        app.read_only = Some(e.to_string());
+        app.push_event(format!("[ERR] Storage not writable ({}): {e} — read-only mode.", storage_path_label(app)));
+    }
+}
+
+/// Whether the storage location accepts writes. A configured directory is
+/// probed with a scratch file. The library does not expose its default
+/// location, so that one is probed by saving the local user record back
+/// unchanged; `None` when there is no record to save yet.
+fn probe_storage(app: &App) -> Option<Result<()>> {
+    match app.storage() {
+        Some(dir) => Some(check_writable(dir).map_err(Into::into)),
+        None => load_local_user(None).ok().map(|user| save_local_user(&user, None)),
     }
-    let ids = list_known_users(None).unwrap_or_default();
+}
+
+/// Probe whether files can be created in `dir`.
+fn check_writable(dir: &std::path::Path) -> std::io::Result<()> {
+    let probe = dir.join(".accord-write-test");
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(&probe)
+}
+
+fn storage_path_label(app: &App) -> String {
+    match &app.storage_dir {
+        Some(dir) => dir.display().to_string(),
+        None => "(library default)".to_string(),
+    }
+}
+
+/// Whether `e` means the storage directory can't be written: permissions, a
+/// read-only filesystem or a full disk. Decided by the `io::Error` in the
+/// chain when there is one.
+fn is_write_failure(e: &anyhow::Error) -> bool {
+    use std::io::ErrorKind;
+    if let Some(io) = e.chain().find_map(|c| c.downcast_ref::<std::io::Error>()) {
+        return matches!(
+            io.kind(),
+            ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem | ErrorKind::StorageFull | ErrorKind::QuotaExceeded
+        );
+    }
+    // Last resort: errors that crossed the node channel arrive as text only,
+    // so match the OS messages for those kinds (English locales only).
+    let text = e.to_string().to_lowercase();
+    ["permission denied", "read-only file system", "no space left", "disk quota exceeded"]
+        .iter()
+        .any(|needle| text.contains(needle))
+}
+
+/// If `e` is a storage write failure, switch to read-only mode and say so
+/// under `title`. Returns whether it was.
+fn storage_write_failed(app: &mut App, title: &str, e: &anyhow::Error) -> bool {
+    if !is_write_failure(e) {
+        return false;
+    }
+    let path = storage_path_label(app);
+    app.read_only = Some(e.to_string());
+    app.push_event(format!("[ERR] Storage not writable ({}): {e}", path));
+    app.set_error(title, vec![
+        format!("Storage not writable: {}", path),
+        format!("  {}", e),
+        String::new(),
+        "Switched to read-only mode: browsing still works, commands that write are refused.".to_string(),
+        "Free disk space or fix the directory's permissions, then run /storage recheck.".to_string(),
+    ]);
+    true
+}
+
+/// Refuse a writing command while in read-only mode. Returns whether it was
+/// refused.
+fn refuse_if_read_only(app: &mut App, title: &str) -> bool {
+    let Some(reason) = app.read_only.clone() else {
+        return false;
+    };
+    app.set_error(title, vec![
+        format!("Storage is read-only ({}): {}", storage_path_label(app), reason),
+        "Fix the storage directory, then run /storage recheck.".to_string(),
+    ]);
+    true
+}
+
+/// Append a notice about skipped `record`s to a listing, if there were any.
+fn push_storage_notice(app: &App, lines: &mut Vec<String>, record: &str) {
+    let skipped = app.storage_issues.iter().filter(|i| i.record == record).count();
+    if skipped > 0 {
+        lines.push(String::new());
+        lines.push(format!("  ⚠ {} record(s) skipped due to read errors — /storage for details.", skipped));
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// Resolve a display-name (nick) to a user ID (case-insensitive), from the
+/// cache if possible and from storage otherwise.
+fn resolve_nick(app: &mut App, nick: &str) -> Option<String> {
+    if let Some(id) = app.nick_cache.get(&nick.to_lowercase()) {
+        return Some(id.clone());
+    }
+    let id = resolve_nick_on_disk(app, nick)?;
+    app.nick_cache.insert(nick.to_lowercase(), id.clone());
+    Some(id)
+}
+
+fn resolve_nick_on_disk(app: &App, nick: &str) -> Option<String> {
+    if let Ok(local) = load_local_user(app.storage())
+        && local.meta.display_name.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(nick))
+    {
+        return Some(local.id);
+    }
+    let ids = list_known_users(app.storage()).unwrap_or_default();
     for id in ids {
-        if let Ok(meta) = load_known_user(&id, None) {
-            if meta.display_name.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(nick)) {
-                return Some(id);
-            }
+        if let Ok(meta) = load_known_user(&id, app.storage())
+            && meta.display_name.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(nick))
+        {
+            return Some(id);
         }
     }
     None
 }
 
+/// Rebuild the nick cache from a full user listing.
+fn cache_nicks<'a>(app: &mut App, users: impl IntoIterator<Item = (Option<&'a str>, &'a str)>) {
+    app.nick_cache.clear();
+    for (name, id) in users {
+        if let Some(name) = name {
+            app.nick_cache.entry(name.to_lowercase()).or_insert_with(|| id.to_string());
+        }
+    }
+}
+
 /// Set the content area to a small list of lines with the given title.
 fn show_lines(app: &mut App, title: &str, lines: Vec<String>) {
     app.set_content(title, lines);
 }
 
 fn split_command(input: &str) -> (&str, &str) {
-    match input.find(' ') {
-        Some(idx) => (&input[..idx], input[idx + 1..].trim_start()),
+    match input.split_once(char::is_whitespace) {
+        Some((cmd, rest)) => (cmd, rest.trim_start()),
         None => (input, ""),
     }
 }
 
-fn truncate_id(id: &str, max: usize) -> String {
+/// An id or hash for a view: shortened to `max` like `truncate_id`, unless
+/// `/ids full` is on.
+fn id_label(app: &App, id: &str, max: usize) -> String {
+    if app.show_full_ids { id.to_string() } else { truncate_id(id, max) }
+}
+
+pub fn truncate_id(id: &str, max: usize) -> String {
     if id.len() <= max {
         id.to_owned()
     } else {
         format!("{}…", &id[..max])
     }
 }
+
+/// Compact form of a libp2p peer id: `12D3…abcd`.
+pub fn short_peer_id(peer_id: &str) -> String {
+    let chars: Vec<char> = peer_id.chars().collect();
+    if chars.len() <= 10 {
+        return peer_id.to_string();
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", head, tail)
+}
+
+/// `text` safe to put on the terminal: tabs become spaces and any other
+/// control character (escape sequences from a peer, say) becomes `�`.
+pub fn printable(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(char::is_control) {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(
+        text.chars()
+            .map(|c| match c {
+                '\t' => ' ',
+                c if c.is_control() => '\u{fffd}',
+                c => c,
+            })
+            .collect(),
+    )
+}
+
+/// Shorten free text to at most `max` characters, char-boundary safe.
+fn truncate_text(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((idx, _)) => format!("{}…", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamped(offset: i64) -> serde_json::Value {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        serde_json::json!({ "text": "hi", "sent_at": now + offset })
+    }
+
+    #[test]
+    fn clock_skew_ignores_small_offsets_and_missing_stamps() {
+        assert_eq!(clock_skew(&stamped(0)), None);
+        assert_eq!(clock_skew(&stamped(-SKEW_LIMIT + 10)), None);
+        assert_eq!(clock_skew(&serde_json::json!({ "text": "hi" })), None);
+    }
+
+    #[test]
+    fn clock_skew_reports_far_offsets_with_their_sign() {
+        let ahead = clock_skew(&stamped(3600)).unwrap();
+        assert!((3595..=3600).contains(&ahead));
+        let behind = clock_skew(&stamped(-7200)).unwrap();
+        assert!((-7200..=-7195).contains(&behind));
+    }
+
+    #[test]
+    fn skew_label_reads_hours_and_direction() {
+        assert_eq!(skew_label(12 * 60), "12m ahead");
+        assert_eq!(skew_label(-(3 * 3600 + 5 * 60)), "3h 5m behind");
+    }
+
+    #[test]
+    fn write_failure_follows_the_io_error_kind() {
+        use std::io::{Error, ErrorKind};
+        let denied = anyhow::Error::from(Error::from(ErrorKind::PermissionDenied)).context("Cannot write user");
+        assert!(is_write_failure(&denied));
+        assert!(is_write_failure(&anyhow::Error::from(Error::from(ErrorKind::StorageFull))));
+        assert!(!is_write_failure(&anyhow::Error::from(Error::from(ErrorKind::NotFound))));
+        // The kind wins over whatever the message says.
+        assert!(!is_write_failure(&anyhow::Error::from(Error::other("permission denied by peer"))));
+    }
+
+    #[test]
+    fn write_failure_falls_back_to_the_message_text() {
+        assert!(is_write_failure(&anyhow!("Permission denied (os error 13)")));
+        assert!(is_write_failure(&anyhow!("No space left on device (os error 28)")));
+        assert!(!is_write_failure(&anyhow!("connection refused")));
+    }
+
+    #[test]
+    fn split_command_separates_name_and_arguments() {
+        assert_eq!(split_command("/message bob  hi there"), ("/message", "bob  hi there"));
+        assert_eq!(split_command("/quit"), ("/quit", ""));
+        assert_eq!(split_command("/set\teventsOrder desc"), ("/set", "eventsOrder desc"));
+    }
+
+    #[test]
+    fn canonical_input_expands_aliases_and_ignores_case() {
+        assert_eq!(canonical_input("/STARTNODE"), "/startNode");
+        assert_eq!(canonical_input("/Q"), "/quit");
+        assert_eq!(canonical_input("/msg bob Hi Bob"), "/message bob Hi Bob");
+        assert_eq!(canonical_input("/nosuch Arg"), "/nosuch Arg");
+    }
+}
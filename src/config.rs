@@ -0,0 +1,379 @@
+use std::{collections::BTreeMap, fmt, fs, path::PathBuf, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+
+use crate::{app::QUICKBAR_KEYS, theme::Theme};
+
+pub const DEFAULT_PORT: u16 = 51030;
+
+/// How often the main loop runs its periodic checks.
+pub const DEFAULT_TICK: Duration = Duration::from_millis(250);
+
+/// Bounds for the tick interval, in milliseconds.
+pub const MIN_TICK_MS: u64 = 50;
+pub const MAX_TICK_MS: u64 = 5_000;
+
+pub const USAGE: &str = "Usage: accord [--port <port>] [--storage-dir <dir>] [--theme <default|light|mono>] [--lock] \
+[--no-autostart] [--events-desc] [--tick-ms <ms>] [--bind F<n>=<command>]... [--exec <command>]...
+Environment: ACCORD_PORT, ACCORD_STORAGE_DIR, ACCORD_THEME, ACCORD_AUTOSTART, ACCORD_TICK_MS (flags take precedence).
+Config file: config.json in the accord/tui config directory (environment and flags take precedence).";
+
+/// Where an effective setting came from, lowest precedence first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    Default,
+    File,
+    Env,
+    Cli,
+    /// Changed at runtime, e.g. by `/port`.
+    Session,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File => write!(f, "config file"),
+            Source::Env => write!(f, "environment"),
+            Source::Cli => write!(f, "command line"),
+            Source::Session => write!(f, "this session"),
+        }
+    }
+}
+
+/// Startup settings for the app.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Directory handed to the storage layer; `None` uses the library default.
+    pub storage_dir: Option<PathBuf>,
+    pub listen_port: u16,
+    pub theme: Theme,
+    pub theme_name: String,
+    /// Start in locked mode (see `/set lock`).
+    pub locked: bool,
+    /// Start the node on launch.
+    pub autostart: bool,
+    /// Show `/events` and `/watch` newest-first.
+    pub events_desc: bool,
+    /// Interval of the main loop's periodic checks.
+    pub tick: Duration,
+    /// Commands bound to F1–F10, indexed from F1.
+    pub quickbar: Vec<Option<String>>,
+    /// Commands run on launch, in order, after the node is auto-started.
+    pub exec: Vec<String>,
+    /// Where each setting that is not at its default came from, by name.
+    pub sources: BTreeMap<&'static str, Source>,
+}
+
+/// Quick-bar bindings used until the user rebinds them.
+fn default_quickbar() -> Vec<Option<String>> {
+    let mut bar = vec![None; QUICKBAR_KEYS];
+    for (i, cmd) in ["/help", "/peers", "/users", "/connections", "/messages", "/events"].iter().enumerate() {
+        bar[i] = Some(cmd.to_string());
+    }
+    bar
+}
+
+/// Parse a function key name such as `F3` into its quick-bar slot.
+pub fn parse_fkey(name: &str) -> Option<usize> {
+    let n: usize = name.strip_prefix(['F', 'f'])?.parse().ok()?;
+    (1..=QUICKBAR_KEYS).contains(&n).then(|| n - 1)
+}
+
+/// Parse a tick interval in milliseconds, within bounds.
+pub fn parse_tick(ms: &str) -> Option<Duration> {
+    let ms: u64 = ms.trim_end_matches("ms").parse().ok()?;
+    (MIN_TICK_MS..=MAX_TICK_MS).contains(&ms).then_some(Duration::from_millis(ms))
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            storage_dir: None,
+            listen_port: DEFAULT_PORT,
+            theme: Theme::default(),
+            theme_name: "default".to_string(),
+            locked: false,
+            autostart: true,
+            events_desc: false,
+            tick: DEFAULT_TICK,
+            quickbar: default_quickbar(),
+            exec: Vec::new(),
+            sources: BTreeMap::new(),
+        }
+    }
+}
+
+/// Settings read from the config file; any of them may be left out.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    port: Option<u16>,
+    storage_dir: Option<PathBuf>,
+    theme: Option<String>,
+    autostart: Option<bool>,
+    lock: Option<bool>,
+    /// `asc` or `desc`.
+    events_order: Option<String>,
+    tick_ms: Option<u64>,
+}
+
+/// Location of the config file.
+pub fn file_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("accord").join("tui").join("config.json"))
+}
+
+/// Write one setting into the config file, keeping everything else in it,
+/// so it applies from the next launch on.
+pub fn save_setting(key: &str, value: serde_json::Value) -> Result<()> {
+    let path = file_path().ok_or_else(|| anyhow!("No config directory available to save settings"))?;
+    let mut settings = match fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&text)
+            .with_context(|| format!("Invalid config file {}", path.display()))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => serde_json::Map::new(),
+        Err(e) => return Err(e).with_context(|| format!("Cannot read {}", path.display())),
+    };
+    settings.insert(key.to_string(), value);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Cannot create {}", parent.display()))?;
+    }
+    fs::write(&path, serde_json::to_vec_pretty(&settings)?)
+        .with_context(|| format!("Cannot write {}", path.display()))
+}
+
+impl Config {
+    /// Build the effective config: command-line flags override `ACCORD_*`
+    /// environment variables, which override the config file, which
+    /// overrides the defaults.
+    pub fn load(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let file = match file_path() {
+            Some(path) => match fs::read_to_string(&path) {
+                Ok(text) => Some((path, text)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e).with_context(|| format!("Cannot read {}", path.display())),
+            },
+            None => None,
+        };
+        Self::resolve(file.as_ref().map(|(path, text)| (path, text.as_str())), |name| std::env::var(name).ok(), args)
+    }
+
+    /// Apply the config file's text, the environment and the arguments, in
+    /// rising precedence, over the defaults.
+    fn resolve(
+        file: Option<(&PathBuf, &str)>,
+        env: impl Fn(&str) -> Option<String>,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self> {
+        let mut config = Config::default();
+        if let Some((path, text)) = file {
+            let parsed: FileConfig =
+                serde_json::from_str(text).with_context(|| format!("Invalid config file {}", path.display()))?;
+            config.apply_file(parsed).with_context(|| format!("In config file {}", path.display()))?;
+        }
+        config.apply_env(env)?;
+        config.apply_args(args)?;
+        Ok(config)
+    }
+
+    /// Where the setting `name` came from.
+    pub fn source(&self, name: &str) -> Source {
+        self.sources.get(name).copied().unwrap_or(Source::Default)
+    }
+
+    fn apply_file(&mut self, file: FileConfig) -> Result<()> {
+        if let Some(port) = file.port {
+            self.set_port(&port.to_string(), Source::File)?;
+        }
+        if let Some(dir) = file.storage_dir {
+            self.storage_dir = Some(dir);
+            self.sources.insert("storage_dir", Source::File);
+        }
+        if let Some(name) = file.theme {
+            self.set_theme(&name, Source::File)?;
+        }
+        if let Some(autostart) = file.autostart {
+            self.autostart = autostart;
+            self.sources.insert("autostart", Source::File);
+        }
+        if let Some(locked) = file.lock {
+            self.locked = locked;
+            self.sources.insert("lock", Source::File);
+        }
+        if let Some(order) = file.events_order {
+            self.events_desc = match order.as_str() {
+                "asc" => false,
+                "desc" => true,
+                _ => bail!("events_order must be asc or desc, got '{}'.", order),
+            };
+            self.sources.insert("events_order", Source::File);
+        }
+        if let Some(ms) = file.tick_ms {
+            self.set_tick(&ms.to_string(), Source::File)?;
+        }
+        Ok(())
+    }
+
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(port) = var("ACCORD_PORT") {
+            self.set_port(&port, Source::Env)?;
+        }
+        if let Some(dir) = var("ACCORD_STORAGE_DIR").filter(|d| !d.is_empty()) {
+            self.storage_dir = Some(PathBuf::from(dir));
+            self.sources.insert("storage_dir", Source::Env);
+        }
+        if let Some(name) = var("ACCORD_THEME") {
+            self.set_theme(&name, Source::Env)?;
+        }
+        if let Some(value) = var("ACCORD_AUTOSTART") {
+            self.autostart = match value.to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => true,
+                "0" | "false" | "no" | "off" => false,
+                _ => bail!("ACCORD_AUTOSTART must be true or false, got '{}'.", value),
+            };
+            self.sources.insert("autostart", Source::Env);
+        }
+        if let Some(ms) = var("ACCORD_TICK_MS") {
+            self.set_tick(&ms, Source::Env)?;
+        }
+        Ok(())
+    }
+
+    /// Apply command-line arguments (without the program name).
+    fn apply_args(&mut self, args: impl IntoIterator<Item = String>) -> Result<()> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| anyhow!("{} requires a value\n{}", arg, USAGE));
+            match arg.as_str() {
+                "--port" => self.set_port(&value()?, Source::Cli)?,
+                "--storage-dir" => {
+                    self.storage_dir = Some(PathBuf::from(value()?));
+                    self.sources.insert("storage_dir", Source::Cli);
+                }
+                "--theme" => self.set_theme(&value()?, Source::Cli)?,
+                "--lock" => {
+                    self.locked = true;
+                    self.sources.insert("lock", Source::Cli);
+                }
+                "--no-autostart" => {
+                    self.autostart = false;
+                    self.sources.insert("autostart", Source::Cli);
+                }
+                "--events-desc" => {
+                    self.events_desc = true;
+                    self.sources.insert("events_order", Source::Cli);
+                }
+                "--tick-ms" => self.set_tick(&value()?, Source::Cli)?,
+                "--bind" => {
+                    let binding = value()?;
+                    let (key, cmd) = binding
+                        .split_once('=')
+                        .ok_or_else(|| anyhow!("--bind expects F<n>=<command>, got '{}'.", binding))?;
+                    let slot = parse_fkey(key)
+                        .ok_or_else(|| anyhow!("'{}' is not a function key (F1–F{}).", key, QUICKBAR_KEYS))?;
+                    let cmd = cmd.trim();
+                    self.quickbar[slot] = (!cmd.is_empty()).then(|| cmd.to_string());
+                    self.sources.insert("quickbar", Source::Cli);
+                }
+                "--exec" => {
+                    let command = value()?.trim().to_string();
+                    if command.is_empty() {
+                        bail!("--exec expects a command, e.g. --exec \"/user alice\".");
+                    }
+                    self.exec.push(if command.starts_with('/') { command } else { format!("/{}", command) });
+                    self.sources.insert("exec", Source::Cli);
+                }
+                _ => bail!("Unknown argument '{}'.\n{}", arg, USAGE),
+            }
+        }
+        Ok(())
+    }
+
+    fn set_port(&mut self, port: &str, source: Source) -> Result<()> {
+        self.listen_port = match port.parse() {
+            Ok(p) if p != 0 => p,
+            _ => bail!("'{}' is not a valid port number (1–65535).", port),
+        };
+        self.sources.insert("port", source);
+        Ok(())
+    }
+
+    fn set_tick(&mut self, ms: &str, source: Source) -> Result<()> {
+        self.tick = match parse_tick(ms) {
+            Some(tick) => tick,
+            None => bail!("'{}' is not a tick interval ({}–{} ms).", ms, MIN_TICK_MS, MAX_TICK_MS),
+        };
+        self.sources.insert("tick", source);
+        Ok(())
+    }
+
+    fn set_theme(&mut self, name: &str, source: Source) -> Result<()> {
+        self.theme = Theme::named(name).ok_or_else(|| anyhow!("Unknown theme '{}'.\n{}", name, USAGE))?;
+        self.theme_name = name.to_string();
+        self.sources.insert("theme", source);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(file: Option<&str>, env: &[(&str, &str)], args: &[&str]) -> Result<Config> {
+        let path = PathBuf::from("config.json");
+        let env: BTreeMap<String, String> = env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Config::resolve(
+            file.map(|text| (&path, text)),
+            |name| env.get(name).cloned(),
+            args.iter().map(|a| a.to_string()),
+        )
+    }
+
+    #[test]
+    fn defaults_apply_without_file_env_or_args() {
+        let config = resolve(None, &[], &[]).unwrap();
+        assert_eq!(config.listen_port, DEFAULT_PORT);
+        assert_eq!(config.source("port"), Source::Default);
+    }
+
+    #[test]
+    fn file_overrides_defaults() {
+        let config = resolve(Some(r#"{"port": 4000, "events_order": "desc"}"#), &[], &[]).unwrap();
+        assert_eq!(config.listen_port, 4000);
+        assert!(config.events_desc);
+        assert_eq!(config.source("port"), Source::File);
+        assert_eq!(config.source("events_order"), Source::File);
+    }
+
+    #[test]
+    fn env_overrides_file() {
+        let config = resolve(Some(r#"{"port": 4000}"#), &[("ACCORD_PORT", "5000")], &[]).unwrap();
+        assert_eq!(config.listen_port, 5000);
+        assert_eq!(config.source("port"), Source::Env);
+    }
+
+    #[test]
+    fn args_override_env_and_file() {
+        let config =
+            resolve(Some(r#"{"port": 4000}"#), &[("ACCORD_PORT", "5000")], &["--port", "6000"]).unwrap();
+        assert_eq!(config.listen_port, 6000);
+        assert_eq!(config.source("port"), Source::Cli);
+    }
+
+    #[test]
+    fn each_setting_keeps_its_own_source() {
+        let config =
+            resolve(Some(r#"{"autostart": false, "tick_ms": 500}"#), &[("ACCORD_TICK_MS", "100")], &[]).unwrap();
+        assert!(!config.autostart);
+        assert_eq!(config.source("autostart"), Source::File);
+        assert_eq!(config.tick, Duration::from_millis(100));
+        assert_eq!(config.source("tick"), Source::Env);
+    }
+
+    #[test]
+    fn bad_file_is_an_error() {
+        assert!(resolve(Some("{ not json"), &[], &[]).is_err());
+        assert!(resolve(Some(r#"{"colour": "red"}"#), &[], &[]).is_err());
+        assert!(resolve(Some(r#"{"events_order": "sideways"}"#), &[], &[]).is_err());
+    }
+}
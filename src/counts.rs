@@ -0,0 +1,55 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Number of messages exchanged with each remote user, keyed by their id and
+/// persisted between sessions.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MessageCounts {
+    counts: HashMap<String, u64>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl MessageCounts {
+    /// Load the counts from their default location, starting from zero if
+    /// the file does not exist yet or cannot be read.
+    pub fn load() -> Self {
+        let path = default_path();
+        let mut counts: MessageCounts = path
+            .as_ref()
+            .and_then(|p| fs::read(p).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        counts.path = path;
+        counts
+    }
+
+    pub fn get(&self, id: &str) -> u64 {
+        self.counts.get(id).copied().unwrap_or(0)
+    }
+
+    /// Count one message exchanged with `id` and persist the counts.
+    pub fn record(&mut self, id: &str) -> Result<()> {
+        *self.counts.entry(id.to_string()).or_default() += 1;
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("No data directory available to store message counts"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Cannot create {}", parent.display()))?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Cannot write {}", path.display()))
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|d| d.join("accord").join("tui").join("message_counts.json"))
+}
@@ -1,27 +1,123 @@
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 
-use crate::{app::App, commands};
+use crate::{
+    app::{App, DisplayLine, Palette, ViewKind},
+    commands,
+};
+
+/// Every key the TUI reacts to, for the `?` overlay.
+pub const KEYBINDINGS: &[(&str, &str)] = &[
+    ("PgUp / PgDn", "Scroll the content"),
+    ("Mouse wheel", "Scroll the content under the pointer"),
+    ("Ctrl+Home / End", "Jump to the top / bottom of the content"),
+    ("↑ / ↓", "Previous / next prompt history entry"),
+    ("← → Home End", "Move in the prompt"),
+    ("Ctrl+A / Ctrl+E", "Start / end of the prompt"),
+    ("Ctrl+U / Ctrl+W", "Delete to the start / the word left of the cursor"),
+    ("Alt+Enter", "New line in the prompt"),
+    ("Tab", "Complete the command name"),
+    ("Enter", "Run the prompt; on an empty one, open / close the top fold in view"),
+    ("Ctrl+P", "Command palette"),
+    ("Ctrl+N", "Next /find match"),
+    ("Ctrl+R", "Re-run the command behind the view"),
+    ("Ctrl+L", "Dismiss the error line"),
+    ("Alt+1–9", "Switch to a kept view (/tab)"),
+    ("F1–F10", "Quick-bar commands (/bind)"),
+    ("?", "This overlay (on an empty prompt)"),
+    ("Esc / Ctrl+C", "Quit (not in locked mode)"),
+    ("Ctrl+Alt+Q", "Quit, even in locked mode"),
+];
 
 /// Handle one key event. Returns `true` if the application should quit.
 pub async fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
-    // Ctrl+C → quit.
-    if key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('c') {
+    // Ctrl+Alt+Q → quit, even in locked mode (the operator's escape hatch).
+    if key.modifiers == KeyModifiers::CONTROL | KeyModifiers::ALT && key.code == KeyCode::Char('q') {
         return Ok(true);
     }
+    // Editing the prompt cancels a pending quit confirmation; running a
+    // command other than /quit does too (see `commands::execute`).
+    if matches!(key.code, KeyCode::Char(_) | KeyCode::Backspace)
+        && !(key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('c'))
+    {
+        app.quit_armed = false;
+    }
+
+    // The palette, while open, takes every other key.
+    if app.palette.is_some() {
+        return handle_palette_key(app, key).await.map(|_| app.should_quit);
+    }
+    // The keybinding overlay closes on Esc or `?` and swallows other keys.
+    if app.show_keys {
+        if matches!(key.code, KeyCode::Esc | KeyCode::Char('?')) {
+            app.show_keys = false;
+        }
+        return Ok(false);
+    }
+    // `?` on an empty prompt → open the keybinding overlay.
+    if key.code == KeyCode::Char('?') && app.prompt_input.is_empty() {
+        app.show_keys = true;
+        return Ok(false);
+    }
+    // Ctrl+P → open the command palette.
+    if key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('p') {
+        app.palette = Some(Palette::default());
+        return Ok(false);
+    }
+    // Ctrl+C → quit (swallowed in locked mode).
+    if key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('c') {
+        return Ok(!app.locked && commands::confirm_quit(app, "Ctrl+C"));
+    }
     // Esc → quit.
-    if key.code == KeyCode::Esc {
-        return Ok(true);
+    if key.code == KeyCode::Esc && !app.locked {
+        return Ok(commands::confirm_quit(app, "Esc"));
+    }
+
+    // Ctrl+N → next /find match.
+    if key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('n') {
+        commands::find_next(app);
+        return Ok(false);
     }
 
-    // Scrolling in content area.
+    // Ctrl+L → dismiss the error line.
+    if key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('l') {
+        app.last_error = None;
+        return Ok(false);
+    }
+
+    // Ctrl+R → refresh the current view.
+    if key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('r') {
+        run_command(app, "/refresh").await;
+        return Ok(false);
+    }
+
+    // Alt+1–9 → switch to that tab.
+    if key.modifiers == KeyModifiers::ALT
+        && let KeyCode::Char(digit @ '1'..='9') = key.code
+    {
+        run_command(app, &format!("/tab {}", digit)).await;
+        return Ok(false);
+    }
+
+    // Scrolling in content area, bounded by what was last drawn so paging
+    // past either end does nothing.
+    let max_scroll = app.content_geometry.max_scroll() as u16;
     match key.code {
         KeyCode::PageUp => {
-            app.content_scroll = app.content_scroll.saturating_sub(10);
+            app.content_scroll = app.content_scroll.min(max_scroll).saturating_sub(10);
             return Ok(false);
         }
         KeyCode::PageDown => {
-            app.content_scroll = app.content_scroll.saturating_add(10);
+            app.content_scroll = app.content_scroll.saturating_add(10).min(max_scroll);
+            return Ok(false);
+        }
+        // Ctrl+Home/End jump to either end; plain Home/End stay with the prompt.
+        KeyCode::Home if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.content_scroll = 0;
+            return Ok(false);
+        }
+        KeyCode::End if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.content_scroll = max_scroll;
             return Ok(false);
         }
         _ => {}
@@ -29,6 +125,15 @@ pub async fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
 
     // Prompt editing and history.
     match key.code {
+        // Alt+Enter (Shift+Enter where the terminal reports it) starts a new
+        // line, for messages longer than a line.
+        KeyCode::Enter
+            if key.modifiers.intersects(KeyModifiers::ALT | KeyModifiers::SHIFT) && !app.prompt_input.is_empty() =>
+        {
+            app.prompt_insert('\n');
+            app.prompt_history_idx = None;
+        }
+
         KeyCode::Enter => {
             let input = app.prompt_input.trim().to_string();
             if !input.is_empty() {
@@ -37,32 +142,67 @@ pub async fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
                     app.prompt_history.push(input.clone());
                 }
                 app.prompt_history_idx = None;
-                app.prompt_input.clear();
+                app.set_prompt("");
+                run_command(app, &input).await;
+            } else {
+                toggle_fold_in_view(app);
+            }
+        }
 
-                if let Err(e) = commands::execute(app, &input).await {
-                    let msg = format!("Error: {e}");
-                    app.push_event(format!("[ERR] {}", e));
-                    app.push_output(msg.clone());
-                    app.content_lines.push(msg);
-                }
+        // Quick-bar: run the command bound to the function key.
+        KeyCode::F(n) => {
+            if let Some(Some(cmd)) = app.quickbar.get((n as usize).wrapping_sub(1)).cloned() {
+                run_command(app, &cmd).await;
             }
         }
 
         KeyCode::Backspace => {
-            app.prompt_input.pop();
+            app.prompt_backspace();
+            app.prompt_history_idx = None;
+        }
+
+        KeyCode::Left => app.prompt_move(-1),
+
+        KeyCode::Right => app.prompt_move(1),
+
+        KeyCode::Home => app.prompt_cursor = 0,
+
+        KeyCode::End => app.prompt_cursor = app.prompt_input.chars().count(),
+
+        // Emacs-style editing.
+        KeyCode::Char('a') if key.modifiers == KeyModifiers::CONTROL => app.prompt_cursor = 0,
+
+        KeyCode::Char('e') if key.modifiers == KeyModifiers::CONTROL => {
+            app.prompt_cursor = app.prompt_input.chars().count();
+        }
+
+        KeyCode::Char('u') if key.modifiers == KeyModifiers::CONTROL => {
+            app.prompt_kill_to_start();
+            app.prompt_history_idx = None;
+        }
+
+        KeyCode::Char('w') if key.modifiers == KeyModifiers::CONTROL => {
+            app.prompt_kill_word();
             app.prompt_history_idx = None;
         }
 
+        KeyCode::Tab => complete_command(app),
+
         KeyCode::Up => scroll_history_up(app),
 
         KeyCode::Down => scroll_history_down(app),
 
+        // Control characters could end up on the terminal when the input is
+        // echoed back, so they are never taken into the prompt.
+        KeyCode::Char(c) if c.is_control() => {}
+
         KeyCode::Char(c) => {
-            // Auto-insert '/' for the first character if nothing typed yet.
-            if app.prompt_input.is_empty() && c != '/' {
-                app.prompt_input.push('/');
+            // Auto-insert '/' before the first character typed into an empty
+            // prompt, unless turned off; leading whitespace never gets one.
+            if app.auto_slash && app.prompt_input.is_empty() && c != '/' && !c.is_whitespace() {
+                app.prompt_insert('/');
             }
-            app.prompt_input.push(c);
+            app.prompt_insert(c);
             app.prompt_history_idx = None;
         }
 
@@ -72,6 +212,107 @@ pub async fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
     Ok(app.should_quit)
 }
 
+/// Insert pasted text at the cursor as it is — no auto `/`, line breaks kept
+/// as prompt newlines, other control characters dropped. Into the palette,
+/// it goes to the filter.
+pub fn handle_paste(app: &mut App, text: &str) {
+    let text: String = text
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .chars()
+        .map(|c| if c == '\t' { ' ' } else { c })
+        .filter(|&c| c == '\n' || !c.is_control())
+        .collect();
+    app.quit_armed = false;
+    if let Some(palette) = app.palette.as_mut() {
+        palette.filter.push_str(&text.replace('\n', " "));
+        palette.selected = 0;
+        return;
+    }
+    app.prompt_insert_str(&text);
+    app.prompt_history_idx = None;
+}
+
+/// Filter, move through and pick from the command palette. Commands that
+/// need arguments are put in the prompt rather than run.
+async fn handle_palette_key(app: &mut App, key: KeyEvent) -> Result<()> {
+    let Some(palette) = app.palette.as_mut() else {
+        return Ok(());
+    };
+    let count = commands::palette_matches(&palette.filter).len();
+    match key.code {
+        KeyCode::Esc => app.palette = None,
+        KeyCode::Char('p') if key.modifiers == KeyModifiers::CONTROL => app.palette = None,
+        KeyCode::Up => palette.selected = palette.selected.saturating_sub(1),
+        KeyCode::Down => palette.selected = (palette.selected + 1).min(count.saturating_sub(1)),
+        KeyCode::Backspace => {
+            palette.filter.pop();
+            palette.selected = 0;
+        }
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+            palette.filter.push(c);
+            palette.selected = 0;
+        }
+        KeyCode::Enter => {
+            let picked = commands::palette_matches(&palette.filter).get(palette.selected).copied();
+            app.palette = None;
+            if let Some((usage, _)) = picked {
+                let (cmd, needs_args) = commands::palette_command(usage);
+                if needs_args {
+                    app.set_prompt(format!("{} ", cmd));
+                    app.prompt_history_idx = None;
+                } else {
+                    run_command(app, &cmd).await;
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Execute a command, turning an error into an error view.
+async fn run_command(app: &mut App, input: &str) {
+    if let Err(e) = commands::execute(app, input).await {
+        let msg = format!("Error: {e}");
+        app.last_error = Some(msg.clone());
+        app.push_event(format!("[ERR] {}", e));
+        app.push_output(msg.clone());
+        app.content_lines.push(msg);
+        app.content_kind = ViewKind::Error;
+    }
+}
+
+/// Complete the command name being typed. One match replaces the input;
+/// several extend it to their common prefix and are listed in the content.
+/// Case is ignored, as it is when the command runs, and completions come
+/// back spelled as in `COMMANDS`.
+fn complete_command(app: &mut App) {
+    let typed = app.prompt_input.as_str();
+    if !typed.starts_with('/') || typed.contains(' ') {
+        return;
+    }
+    let lowered = typed.to_lowercase();
+    let matches: Vec<&str> = commands::command_names()
+        .into_iter()
+        .filter(|name| name.to_lowercase().starts_with(&lowered))
+        .collect();
+    match matches.as_slice() {
+        [] => {}
+        [only] => app.set_prompt(*only),
+        [first, rest @ ..] => {
+            let common = rest.iter().fold(first.len(), |len, name| {
+                first.bytes().zip(name.bytes()).take(len).take_while(|(a, b)| a == b).count()
+            });
+            let common = first[..common].to_string();
+            let mut lines = vec![format!("Commands starting with {}:", typed)];
+            lines.extend(matches.iter().map(|name| format!("  {}", name)));
+            app.set_prompt(common);
+            app.set_content("Completions", lines);
+        }
+    }
+}
+
 fn scroll_history_up(app: &mut App) {
     if app.prompt_history.is_empty() {
         return;
@@ -81,7 +322,7 @@ fn scroll_history_up(app: &mut App) {
         Some(i) => i.saturating_sub(1),
     };
     app.prompt_history_idx = Some(new_idx);
-    app.prompt_input = app.prompt_history[new_idx].clone();
+    app.set_prompt(app.prompt_history[new_idx].clone());
 }
 
 fn scroll_history_down(app: &mut App) {
@@ -91,11 +332,145 @@ fn scroll_history_down(app: &mut App) {
             if i + 1 < app.prompt_history.len() {
                 let new_idx = i + 1;
                 app.prompt_history_idx = Some(new_idx);
-                app.prompt_input = app.prompt_history[new_idx].clone();
+                app.set_prompt(app.prompt_history[new_idx].clone());
             } else {
                 app.prompt_history_idx = None;
-                app.prompt_input.clear();
+                app.set_prompt("");
+            }
+        }
+    }
+}
+
+/// Bring the content scroll back within the resized viewport, so a shrunk
+/// terminal doesn't keep an offset past the last line.
+pub fn handle_resize(app: &mut App) {
+    let max_scroll = app.content_geometry.max_scroll() as u16;
+    app.content_scroll = app.content_scroll.min(max_scroll);
+    app.scrollbar_drag = false;
+}
+
+/// Lines moved per mouse wheel notch.
+const WHEEL_STEP: u16 = 3;
+
+/// Handle one mouse event: the wheel scrolls the content under the pointer,
+/// dragging the content scrollbar's thumb scrolls proportionally, clicking
+/// its track pages towards the click.
+pub fn handle_mouse(app: &mut App, mouse: MouseEvent) {
+    let geo = app.content_geometry;
+    let bar = geo.scrollbar;
+    let max_scroll = geo.max_scroll();
+    let on_bar = mouse.column == bar.x && mouse.row >= bar.y && mouse.row < bar.y + bar.height;
+    let area = geo.area;
+    let on_content = mouse.column >= area.x
+        && mouse.column < area.x + area.width
+        && mouse.row >= area.y
+        && mouse.row < area.y + area.height;
+
+    match mouse.kind {
+        MouseEventKind::ScrollUp if on_content => {
+            let current = app.content_scroll.min(max_scroll as u16);
+            app.content_scroll = current.saturating_sub(WHEEL_STEP);
+        }
+        MouseEventKind::ScrollDown if on_content => {
+            let down = (app.content_scroll as usize + WHEEL_STEP as usize).min(max_scroll);
+            app.content_scroll = down as u16;
+        }
+        MouseEventKind::Down(MouseButton::Left) if on_bar && max_scroll > 0 => {
+            let (thumb_start, thumb_len) = thumb(app);
+            let row = mouse.row - bar.y;
+            // Clamped first: views that follow their end scroll past it.
+            let current = (app.content_scroll as usize).min(max_scroll);
+            if row < thumb_start {
+                app.content_scroll = current.saturating_sub(geo.visible) as u16;
+            } else if row >= thumb_start + thumb_len {
+                app.content_scroll = (current + geo.visible).min(max_scroll) as u16;
+            } else {
+                app.scrollbar_drag = true;
             }
         }
+        MouseEventKind::Drag(MouseButton::Left) if app.scrollbar_drag => {
+            // The inverse of `thumb`: the thumb's start travels over the
+            // track less its own length.
+            let (_, thumb_len) = thumb(app);
+            let travel = bar.height.saturating_sub(thumb_len).max(1) as usize;
+            let row = (mouse.row.saturating_sub(bar.y) as usize).min(travel);
+            app.content_scroll = (row * max_scroll / travel) as u16;
+        }
+        MouseEventKind::Down(MouseButton::Left) if !on_bar => toggle_fold_at(app, mouse.row),
+        MouseEventKind::Up(MouseButton::Left) => app.scrollbar_drag = false,
+        _ => {}
+    }
+}
+
+/// Open or close the fold whose header is drawn on screen row `row`.
+fn toggle_fold_at(app: &mut App, row: u16) {
+    let geo = app.content_geometry;
+    let inner_top = geo.area.y + 1;
+    if row < inner_top || row >= inner_top + geo.visible as u16 {
+        return;
+    }
+    let offset = (app.content_scroll as usize).min(geo.max_scroll());
+    let index = offset + (row - inner_top) as usize;
+    if let Some(DisplayLine::Fold(idx)) = app.display_lines().get(index).copied() {
+        app.content_folds[idx].open = !app.content_folds[idx].open;
+    }
+}
+
+/// Open or close the topmost fold header on screen, if any.
+fn toggle_fold_in_view(app: &mut App) {
+    let geo = app.content_geometry;
+    let offset = (app.content_scroll as usize).min(geo.max_scroll());
+    let header = app.display_lines().into_iter().skip(offset).take(geo.visible).find_map(|line| match line {
+        DisplayLine::Fold(idx) => Some(idx),
+        _ => None,
+    });
+    if let Some(idx) = header {
+        app.content_folds[idx].open = !app.content_folds[idx].open;
+    }
+}
+
+/// Start row (relative to the track) and length of the scrollbar thumb.
+fn thumb(app: &App) -> (u16, u16) {
+    let geo = app.content_geometry;
+    let track = geo.scrollbar.height as usize;
+    let max_scroll = geo.max_scroll().max(1);
+    let len = (track * geo.visible / geo.total.max(1)).clamp(1, track);
+    let offset = (app.content_scroll as usize).min(max_scroll);
+    let start = (track - len) * offset / max_scroll;
+    (start as u16, len as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::layout::Rect;
+
+    use super::*;
+    use crate::app::ContentGeometry;
+
+    fn scrolled(total: usize, scroll: u16) -> App {
+        let mut app = App::default();
+        app.content_geometry = ContentGeometry {
+            area: Rect::new(0, 0, 40, 22),
+            scrollbar: Rect::new(39, 1, 1, 20),
+            visible: 20,
+            total,
+        };
+        app.content_scroll = scroll;
+        app
+    }
+
+    #[test]
+    fn thumb_size_follows_the_visible_share() {
+        assert_eq!(thumb(&scrolled(100, 0)), (0, 4));
+        assert_eq!(thumb(&scrolled(10_000, 0)).1, 1);
+        assert_eq!(thumb(&scrolled(20, 0)), (0, 20));
+    }
+
+    #[test]
+    fn thumb_position_follows_the_scroll_and_stays_on_the_track() {
+        assert_eq!(thumb(&scrolled(100, 40)), (8, 4));
+        assert_eq!(thumb(&scrolled(100, 80)), (16, 4));
+        // A scroll left past the end (the view shrank) pins the thumb to the bottom.
+        assert_eq!(thumb(&scrolled(100, 500)), (16, 4));
     }
 }
@@ -1,38 +1,85 @@
 use anyhow::Result;
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture, Event, EventStream},
+    event::{
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, EventStream,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use futures::StreamExt;
+use accord_network::Message;
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
-use tokio::time::{sleep, Duration};
+use tokio::{
+    sync::mpsc,
+    time::{interval, Duration, Instant},
+};
 
 mod app;
 mod commands;
+mod config;
+mod counts;
 mod events;
+mod node;
+mod theme;
+mod trust;
 mod ui;
 
 use app::App;
+use config::Config;
+use node::NodeEvent;
+
+/// How often content that changes with time alone (relative timestamps,
+/// the sparkline) is redrawn while nothing else happens.
+const ANIMATION_REDRAW: Duration = Duration::from_secs(1);
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let config = Config::load(std::env::args().skip(1))?;
+    let autostart = config.autostart;
+    let exec = config.exec.clone();
+
+    // Route the node's internal logging into the events view; anything it
+    // printed to stdout would corrupt the alternate screen.
+    let (node_events_tx, mut node_events_rx) = mpsc::unbounded_channel();
+    node::init_logging(node_events_tx.clone());
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    // Bracketed paste delivers a paste as one event instead of keystrokes.
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
 
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new();
+    // Messages received from peers, shown as they arrive.
+    let (inbox_tx, mut inbox_rx) = mpsc::unbounded_channel();
+
+    let mut app = App::with_config(config, node_events_tx, inbox_tx);
+    commands::check_storage(&mut app);
 
     // Auto-start the node on launch as required by the plan.
-    if let Err(e) = commands::execute(&mut app, "/startNode").await {
+    if autostart && let Err(e) = commands::execute(&mut app, "/startNode").await {
         app.push_event(format!("[NODE] Auto-start failed: {e}"));
     }
+    // Then whatever --exec asked for, in order; a failure doesn't stop the rest.
+    for command in &exec {
+        app.push_event(format!("[APP] --exec {}", command));
+        if let Err(e) = commands::execute(&mut app, command).await {
+            app.push_event(format!("[ERR] --exec {} failed: {e}", command));
+            app.last_error = Some(format!("{} failed: {e}", command));
+        }
+    }
+
+    let result = run(&mut terminal, &mut app, &mut node_events_rx, &mut inbox_rx).await;
 
-    let result = run(&mut terminal, &mut app).await;
+    // Shut the node down cleanly however the loop ended — quit, end of
+    // input or an error — so it releases its listen port before we exit.
+    if app.node_tx.is_some()
+        && let Err(e) = commands::execute(&mut app, "/stopNode").await
+    {
+        app.push_event(format!("[NODE] Stop failed: {e}"));
+    }
 
     // Always restore the terminal, even on error.
     disable_raw_mode()?;
@@ -40,6 +87,7 @@ async fn main() -> Result<()> {
         terminal.backend_mut(),
         LeaveAlternateScreen,
         DisableMouseCapture,
+        DisableBracketedPaste,
     )?;
     terminal.show_cursor()?;
 
@@ -49,17 +97,52 @@ async fn main() -> Result<()> {
 async fn run<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
+    node_events: &mut mpsc::UnboundedReceiver<NodeEvent>,
+    inbox: &mut mpsc::UnboundedReceiver<Message>,
 ) -> Result<()> {
     let mut reader = EventStream::new();
+    // An interval rather than a fresh sleep per iteration, so a steady stream
+    // of node events cannot starve the periodic work.
+    let mut tick = interval(app.tick_interval);
+    let mut signals = Signals::new()?;
+    // Only draw when something changed: input, node events, or a tick that
+    // did something. Time-driven content is redrawn at most once a second.
+    let mut dirty = true;
+    let mut last_draw = Instant::now();
 
     loop {
-        terminal.draw(|f| ui::render(f, app))?;
-
-        let tick = sleep(Duration::from_millis(250));
+        if dirty {
+            terminal.draw(|f| ui::render(f, app))?;
+            dirty = false;
+            last_draw = Instant::now();
+        }
+        if tick.period() != app.tick_interval {
+            tick = interval(app.tick_interval);
+        }
 
         tokio::select! {
-            _ = tick => {
-                // Periodic refresh — re-draw even without input so the UI stays alive.
+            _ = tick.tick() => {
+                let before = app.screen_version();
+                commands::check_pending_acks(app);
+                commands::follow_peers(app);
+                commands::check_connect_wait(app);
+                commands::check_pings(app);
+                app.activity.tick();
+                dirty = app.screen_version() != before
+                    || (app.animating() && last_draw.elapsed() >= ANIMATION_REDRAW);
+                continue;
+            }
+            Some(event) = node_events.recv() => {
+                node::handle_event(app, event).await;
+            }
+            Some(message) = inbox.recv() => {
+                commands::receive_message(app, message);
+            }
+            name = signals.recv() => {
+                // Shut down as cleanly as /quit would; main stops the node
+                // and restores the terminal once the loop exits.
+                app.push_event(format!("[APP] {} received — shutting down.", name));
+                break;
             }
             maybe_event = reader.next() => {
                 match maybe_event {
@@ -69,13 +152,64 @@ async fn run<B: ratatui::backend::Backend>(
                             break;
                         }
                     }
-                    Some(Ok(_)) => {} // mouse events, resize, etc.
+                    Some(Ok(Event::Mouse(mouse))) => events::handle_mouse(app, mouse),
+                    Some(Ok(Event::Paste(text))) => events::handle_paste(app, &text),
+                    Some(Ok(Event::Resize(_, _))) => {
+                        // Lay out at the new size first, so the scroll is
+                        // clamped against the viewport as it is now.
+                        terminal.draw(|f| ui::render(f, app))?;
+                        events::handle_resize(app);
+                    }
+                    Some(Ok(_)) => {} // focus, etc.
                     Some(Err(e)) => return Err(e.into()),
                     None => break,
                 }
             }
         }
+        dirty = true;
     }
 
     Ok(())
 }
+
+/// Termination requests from outside the terminal: process managers send
+/// SIGTERM, `kill -INT` sends SIGINT (Ctrl+C arrives as a key in raw mode).
+#[cfg(unix)]
+struct Signals {
+    term: tokio::signal::unix::Signal,
+    int: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl Signals {
+    fn new() -> io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(Self {
+            term: signal(SignalKind::terminate())?,
+            int: signal(SignalKind::interrupt())?,
+        })
+    }
+
+    /// Wait for the next signal and return its name.
+    async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.term.recv() => "SIGTERM",
+            _ = self.int.recv() => "SIGINT",
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct Signals;
+
+#[cfg(not(unix))]
+impl Signals {
+    fn new() -> io::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn recv(&mut self) -> &'static str {
+        let _ = tokio::signal::ctrl_c().await;
+        "Ctrl+C"
+    }
+}
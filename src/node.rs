@@ -0,0 +1,372 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use accord_network::storage::fs::{list_connections, load_connection, load_local_user, load_peers};
+use tokio::{
+    sync::mpsc,
+    task::JoinHandle,
+    time::{interval, Duration},
+};
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{filter::Targets, layer::Context, prelude::*, Layer};
+
+use crate::{
+    app::{App, ViewKind},
+    commands,
+};
+
+/// How often the watcher re-reads node state from storage.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+// FullNode does not hand out its swarm events, so mDNS discovery is read off
+// the INFO records libp2p-mdns logs for them. These are the record messages
+// as emitted by libp2p-mdns 0.48 (libp2p 0.56, see Cargo.lock); they are not
+// a stable API, so re-check them whenever libp2p is bumped.
+const MDNS_TARGET: &str = "libp2p_mdns";
+const MDNS_DISCOVERED: &str = "discovered peer on address";
+const MDNS_EXPIRED: &str = "expired peer on address";
+
+// FullNode has no accessor for its libp2p peer id and keeps the swarm keypair
+// to itself, so the id is taken from the record the node logs at startup.
+// A `local_peer_id` field wins; otherwise the message must contain
+// `LOCAL_PEER_ID_MARKER` and the id is the first token shaped like a
+// libp2p-identity 0.2 peer id: Ed25519 ids start with `12D3Koo`, legacy
+// RSA ones are 46-character `Qm` multihashes.
+const LOCAL_PEER_ID_MARKER: &str = "local peer id";
+const ED25519_PEER_ID_PREFIX: &str = "12D3Koo";
+const LEGACY_PEER_ID_PREFIX: &str = "Qm";
+const LEGACY_PEER_ID_LEN: usize = 46;
+
+/// Something the node reported on its own, outside of a command reply.
+#[derive(Debug, Clone)]
+pub enum NodeEvent {
+    /// A diagnostic emitted by the node or the libp2p stack underneath it.
+    Log {
+        level: Level,
+        target: String,
+        message: String,
+    },
+    /// A peer became known, either announced by mDNS (with the address it
+    /// was seen on) or newly persisted.
+    PeerDiscovered {
+        peer: String,
+        via: Option<&'static str>,
+        addr: Option<String>,
+    },
+    /// A peer's mDNS record for one address expired.
+    PeerLost { peer: String, addr: Option<String> },
+    /// The node announced its own libp2p peer id.
+    LocalPeerId(String),
+    /// A stored connection appeared or moved between pending and established.
+    ConnectionChanged {
+        from_id: String,
+        to_id: String,
+        established: bool,
+    },
+}
+
+/// Install a global tracing subscriber that forwards the node's internal log
+/// output into `tx` instead of writing it over the TUI.
+///
+/// The node itself is logged from INFO upwards; the libp2p internals only
+/// from WARN, otherwise gossip chatter drowns everything else. mDNS is the
+/// exception since its INFO records carry peer discovery.
+pub fn init_logging(tx: mpsc::UnboundedSender<NodeEvent>) {
+    let filter = Targets::new()
+        .with_default(Level::WARN)
+        .with_target("accord_network", Level::INFO)
+        .with_target(MDNS_TARGET, Level::INFO)
+        .with_target("network", Level::INFO);
+
+    // Ignore the error: a subscriber may already be installed, in which case
+    // node output simply goes wherever that one sends it.
+    let _ = tracing_subscriber::registry()
+        .with(ChannelLayer { tx }.with_filter(filter))
+        .try_init();
+}
+
+/// Spawn the background task that watches the node's on-disk state while it
+/// runs. The task is aborted by `/stopNode`.
+pub fn spawn_watcher(tx: mpsc::UnboundedSender<NodeEvent>, storage_dir: Option<PathBuf>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let dir = storage_dir.as_deref();
+        let mut known: HashSet<String> = load_peers(dir).unwrap_or_default().into_iter().collect();
+        let mut states = connection_states(dir);
+        let mut ticker = interval(WATCH_INTERVAL);
+        loop {
+            ticker.tick().await;
+
+            let mut events = Vec::new();
+            for peer in load_peers(dir).unwrap_or_default() {
+                if known.insert(peer.clone()) {
+                    events.push(NodeEvent::PeerDiscovered { peer, via: None, addr: None });
+                }
+            }
+
+            let current = connection_states(dir);
+            for ((from_id, to_id), &established) in &current {
+                if states.get(&(from_id.clone(), to_id.clone())) != Some(&established) {
+                    events.push(NodeEvent::ConnectionChanged {
+                        from_id: from_id.clone(),
+                        to_id: to_id.clone(),
+                        established,
+                    });
+                }
+            }
+            states = current;
+
+            for event in events {
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+    })
+}
+
+/// Snapshot of every stored connection's state, keyed by `(from_id, to_id)`.
+fn connection_states(dir: Option<&Path>) -> HashMap<(String, String), bool> {
+    let from_id = match load_local_user(dir) {
+        Ok(user) => user.id,
+        Err(_) => return HashMap::new(),
+    };
+    list_connections(dir)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|to_id| load_connection(&from_id, to_id, dir).ok())
+        .map(|c| {
+            let established = c.is_established();
+            ((c.from_id, c.to_id), established)
+        })
+        .collect()
+}
+
+/// Apply one node event to the app state.
+pub async fn handle_event(app: &mut App, event: NodeEvent) {
+    match event {
+        NodeEvent::Log { level, target, message } => {
+            app.push_event(format!("[NODE] {:<5} {}: {}", level, target, message));
+        }
+        NodeEvent::PeerDiscovered { peer, via, addr } => {
+            if via.is_some() {
+                let addrs = app.live_peers.entry(peer.clone()).or_default();
+                if let Some(addr) = addr
+                    && !addrs.contains(&addr)
+                {
+                    addrs.push(addr);
+                }
+                refresh_net_peers_view(app);
+            }
+            if app.peers.contains(&peer) {
+                return;
+            }
+            match via {
+                Some(via) => app.push_event(format!("[PEERS] discovered {} via {}", peer, via)),
+                None => app.push_event(format!("[PEERS] discovered {}", peer)),
+            }
+            app.peers.push(peer);
+            refresh_peers_view(app);
+        }
+        NodeEvent::PeerLost { peer, addr } => {
+            if let Some(addrs) = app.live_peers.get_mut(&peer) {
+                addrs.retain(|a| Some(a) != addr.as_ref());
+                if addr.is_none() || addrs.is_empty() {
+                    app.live_peers.remove(&peer);
+                }
+                refresh_net_peers_view(app);
+            }
+            // Still reachable over another address or transport.
+            if app.live_peers.contains_key(&peer) || !app.peers.contains(&peer) {
+                return;
+            }
+            app.push_event(format!("[PEERS] lost {}", peer));
+            app.peers.retain(|p| p != &peer);
+            refresh_peers_view(app);
+        }
+        NodeEvent::LocalPeerId(peer_id) => {
+            if app.local_peer_id.as_ref() != Some(&peer_id) {
+                app.push_event(format!("[NODE] Local peer id: {}", peer_id));
+                app.local_peer_id = Some(peer_id);
+            }
+        }
+        NodeEvent::ConnectionChanged { from_id, to_id, established } => {
+            let conn = match load_connection(&from_id, &to_id, app.storage()) {
+                Ok(conn) => conn,
+                Err(_) => return,
+            };
+            let local_id = commands::local_id(app).unwrap_or_default();
+            if commands::is_dropped_request(app, &conn, &local_id) {
+                return;
+            }
+            let times = app.connection_times.entry((from_id.clone(), to_id.clone())).or_default();
+            let now = SystemTime::now();
+            if established {
+                times.accepted.get_or_insert(now);
+            } else {
+                times.created.get_or_insert(now);
+            }
+            if established {
+                app.push_event(format!(
+                    "[CONN] 🔒 Key exchange complete with {} — conversation is end-to-end encrypted.",
+                    commands::truncate_id(&to_id, 16)
+                ));
+                if app.connect_wait.as_ref().is_some_and(|w| w.from_id == from_id && w.to_id == to_id)
+                    && let Some(wait) = app.connect_wait.take()
+                {
+                    app.push_output(format!("Connection with {} established.", wait.nick));
+                }
+            } else if local_id == to_id {
+                commands::offer_connection(app, &conn).await;
+            } else {
+                app.push_event(format!("[CONN] 🔓 Pending connection with {}.", commands::truncate_id(&to_id, 16)));
+            }
+
+            match app.connections.iter().position(|c| c.from_id == from_id && c.to_id == to_id) {
+                Some(i) => app.connections[i] = conn.clone(),
+                None => app.connections.push(conn.clone()),
+            }
+
+            let viewing = app.connection_view.as_ref() == Some(&(from_id, to_id));
+            if viewing && app.content_kind == ViewKind::Connections {
+                let heading = if established {
+                    "Connection  [established]"
+                } else {
+                    "Connection  [pending]"
+                };
+                let title = app.content_title.trim().to_string();
+                commands::show_connection(app, &title, heading, &conn);
+            }
+        }
+    }
+}
+
+fn refresh_peers_view(app: &mut App) {
+    if app.peers_follow && app.content_kind == ViewKind::Peers {
+        commands::show_peers(app);
+    }
+}
+
+fn refresh_net_peers_view(app: &mut App) {
+    if app.content_kind == ViewKind::NetPeers {
+        commands::show_net_peers(app);
+    }
+}
+
+struct ChannelLayer {
+    tx: mpsc::UnboundedSender<NodeEvent>,
+}
+
+impl<S: Subscriber> Layer<S> for ChannelLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let target = meta.target();
+        if (target.starts_with("accord_network") || target.starts_with("network"))
+            && let Some(peer_id) = visitor.local_peer_id()
+        {
+            let _ = self.tx.send(NodeEvent::LocalPeerId(peer_id));
+        }
+
+        // libp2p-mdns reports discovery and expiry as INFO records carrying
+        // the peer id; turn those into proper peer events.
+        if target.starts_with(MDNS_TARGET) {
+            if let Some(peer) = visitor.peer.take() {
+                let event = match visitor.message.as_str() {
+                    MDNS_DISCOVERED => Some(NodeEvent::PeerDiscovered {
+                        peer,
+                        via: Some("mDNS"),
+                        addr: visitor.address.take(),
+                    }),
+                    MDNS_EXPIRED => Some(NodeEvent::PeerLost { peer, addr: visitor.address.take() }),
+                    _ => None,
+                };
+                if let Some(event) = event {
+                    let _ = self.tx.send(event);
+                }
+            }
+            if *meta.level() > Level::WARN {
+                return;
+            }
+        }
+
+        let _ = self.tx.send(NodeEvent::Log {
+            level: *meta.level(),
+            target: meta.target().to_string(),
+            message: visitor.finish(),
+        });
+    }
+}
+
+/// Flattens a tracing event into `message key=value key=value`.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    peer: Option<String>,
+    address: Option<String>,
+    local_peer_id: Option<String>,
+    fields: Vec<String>,
+}
+
+impl MessageVisitor {
+    /// The node's own peer id, if this record announces it — either as a
+    /// `local_peer_id` field or in a message like "Local peer id: 12D3Koo…".
+    fn local_peer_id(&self) -> Option<String> {
+        if let Some(id) = &self.local_peer_id {
+            return Some(id.clone());
+        }
+        if !self.message.to_lowercase().contains(LOCAL_PEER_ID_MARKER) {
+            return None;
+        }
+        self.message
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .find(|token| {
+                token.starts_with(ED25519_PEER_ID_PREFIX)
+                    || (token.starts_with(LEGACY_PEER_ID_PREFIX) && token.len() == LEGACY_PEER_ID_LEN)
+            })
+            .map(str::to_string)
+    }
+
+    fn finish(self) -> String {
+        let mut out = self.message;
+        for field in self.fields {
+            if !out.is_empty() {
+                out.push(' ');
+            }
+            out.push_str(&field);
+        }
+        out
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.push(format!("{}={}", field.name(), value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            match field.name() {
+                "peer" => self.peer = Some(format!("{:?}", value)),
+                "address" => self.address = Some(format!("{:?}", value)),
+                "local_peer_id" => self.local_peer_id = Some(format!("{:?}", value)),
+                _ => {}
+            }
+            self.fields.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use ratatui::style::{Color, Style};
+
+use crate::app::{ViewKind, RECEIVED_MARK, SENT_MARK};
+
+/// Leader of a colour line in `/legend`; the colour's name follows it.
+pub const SWATCH: &str = "  ■ ";
+
+/// How serious a log line is, judged from its tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Severity {
+    /// Classify an events/console line by its leading tag.
+    pub fn of(line: &str) -> Self {
+        if line.starts_with("[ERR]") || line.starts_with("[NODE] ERROR") {
+            Severity::Error
+        } else if line.starts_with("[NODE] WARN") {
+            Severity::Warn
+        } else {
+            Severity::Info
+        }
+    }
+}
+
+/// Colours used across the UI.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub header: Color,
+    pub text: Color,
+    /// Border of the prompt and of views without an accent of their own.
+    pub border: Color,
+    pub help: Color,
+    pub network: Color,
+    pub messages: Color,
+    pub warn: Color,
+    pub error: Color,
+    /// Connections we initiated.
+    pub outbound: Color,
+    /// Connections the remote initiated.
+    pub inbound: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            header: Color::Cyan,
+            text: Color::White,
+            border: Color::DarkGray,
+            help: Color::Blue,
+            network: Color::Cyan,
+            messages: Color::Green,
+            warn: Color::Yellow,
+            error: Color::Red,
+            outbound: Color::LightBlue,
+            inbound: Color::LightMagenta,
+        }
+    }
+}
+
+impl Theme {
+    /// Look up a built-in theme by name.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "light" => Some(Self {
+                header: Color::Blue,
+                text: Color::Black,
+                border: Color::Gray,
+                help: Color::Blue,
+                network: Color::Magenta,
+                messages: Color::Green,
+                warn: Color::Rgb(176, 112, 0),
+                error: Color::Red,
+                outbound: Color::Blue,
+                inbound: Color::Magenta,
+            }),
+            "mono" => Some(Self {
+                header: Color::Reset,
+                text: Color::Reset,
+                border: Color::Reset,
+                help: Color::Reset,
+                network: Color::Reset,
+                messages: Color::Reset,
+                warn: Color::Reset,
+                error: Color::Reset,
+                outbound: Color::Reset,
+                inbound: Color::Reset,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Border and title colour for a content view.
+    pub fn accent(&self, kind: ViewKind, lines: &[String]) -> Color {
+        match kind {
+            ViewKind::Info | ViewKind::Console => self.border,
+            ViewKind::Help | ViewKind::Legend => self.help,
+            ViewKind::Peers | ViewKind::NetPeers | ViewKind::Users | ViewKind::Connections => self.network,
+            ViewKind::Messages => self.messages,
+            ViewKind::Error => self.error,
+            ViewKind::Events | ViewKind::Watch => match lines.iter().map(|l| Severity::of(l)).max() {
+                Some(Severity::Error) => self.error,
+                Some(Severity::Warn) => self.warn,
+                _ => self.border,
+            },
+        }
+    }
+
+    /// Colour of an event line, by severity and then by its `[TAG]`.
+    fn event_color(&self, line: &str) -> Option<Color> {
+        match Severity::of(line) {
+            Severity::Error => return Some(self.error),
+            Severity::Warn => return Some(self.warn),
+            Severity::Info => {}
+        }
+        if line.starts_with("[CONN]") {
+            let pending = line.contains("🔓") || line.contains("Connection request");
+            return Some(if pending { self.warn } else { self.network });
+        }
+        if line.contains("⚠") {
+            Some(self.warn)
+        } else if line.starts_with("[MSG]") {
+            Some(self.messages)
+        } else if line.starts_with("[PEERS]") {
+            Some(self.network)
+        } else {
+            None
+        }
+    }
+
+    /// Every colour with its name and what it marks, for `/legend`.
+    pub fn swatches(&self) -> [(&'static str, Color, &'static str); 10] {
+        [
+            ("header", self.header, "Title bar and quick-bar keys"),
+            ("text", self.text, "Prompt input"),
+            ("border", self.border, "Quiet views, timestamps, earlier outputs, the command behind a view"),
+            ("help", self.help, "Help, legend and the command palette"),
+            ("network", self.network, "Peers, users and connections views; [PEERS] and [CONN] events"),
+            ("messages", self.messages, "Messages view, [MSG] events and the activity sparkline"),
+            ("warn", self.warn, "Warnings, pending connections; a backed-up send queue"),
+            ("error", self.error, "Errors and the events reporting them"),
+            ("outbound", self.outbound, "Connections you initiated; messages you sent"),
+            ("inbound", self.inbound, "Connections the other side initiated; messages you received"),
+        ]
+    }
+
+    /// Style for a single content line, based on what the view shows.
+    pub fn line_style(&self, kind: ViewKind, line: &str) -> Style {
+        match kind {
+            ViewKind::Legend => {
+                let name = line.strip_prefix(SWATCH).and_then(|rest| rest.split_whitespace().next());
+                match self.swatches().iter().find(|(n, _, _)| Some(*n) == name) {
+                    Some((_, color, _)) => Style::default().fg(*color),
+                    None => Style::default(),
+                }
+            }
+            ViewKind::Connections if line.contains("↑ outbound") => Style::default().fg(self.outbound),
+            ViewKind::Connections if line.contains("↓ inbound") => Style::default().fg(self.inbound),
+            ViewKind::Events | ViewKind::Watch => match self.event_color(line) {
+                Some(color) => Style::default().fg(color),
+                None => Style::default(),
+            },
+            ViewKind::Messages if line.starts_with(SENT_MARK) => Style::default().fg(self.outbound),
+            ViewKind::Messages if line.starts_with(RECEIVED_MARK) => Style::default().fg(self.inbound),
+            _ => Style::default(),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// How a peer's current key compares to what we have on record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStatus {
+    Unverified,
+    Verified,
+    /// The peer presents a different key than the one previously seen or
+    /// verified.
+    Changed,
+}
+
+/// Keys seen for remote users and which of them the user has verified out of
+/// band, persisted between sessions.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+    /// Public key each verified peer had at the time it was verified.
+    verified: HashMap<String, String>,
+    /// Public key each remote user was last accepted under (trust on first use).
+    #[serde(default)]
+    seen: HashMap<String, String>,
+    /// Users whose connection requests were declined; their pending requests
+    /// stay hidden until one is accepted.
+    #[serde(default)]
+    declined: HashSet<String>,
+    /// Users we withdrew our own pending requests to; those stay hidden
+    /// until a new connection to them is initiated.
+    #[serde(default)]
+    cancelled: HashSet<String>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl TrustStore {
+    /// Load the store from its default location, starting empty if it does
+    /// not exist yet or cannot be read.
+    pub fn load() -> Self {
+        let path = default_path();
+        let mut store: TrustStore = path
+            .as_ref()
+            .and_then(|p| fs::read(p).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        store.path = path;
+        store
+    }
+
+    pub fn status(&self, id: &str, public_key: &str) -> KeyStatus {
+        match self.verified.get(id) {
+            Some(key) if key == public_key => KeyStatus::Verified,
+            Some(_) => KeyStatus::Changed,
+            None if self.seen.get(id).is_some_and(|key| key != public_key) => KeyStatus::Changed,
+            None => KeyStatus::Unverified,
+        }
+    }
+
+    /// Remember `public_key` for `id` if this is the first time we see the
+    /// user. A differing key is never recorded here; see `accept_key`.
+    pub fn observe(&mut self, id: &str, public_key: &str) -> Result<()> {
+        if self.seen.contains_key(id) {
+            return Ok(());
+        }
+        self.seen.insert(id.to_string(), public_key.to_string());
+        self.save()
+    }
+
+    /// Accept a changed key. A verification made under the old key no longer
+    /// applies and is dropped. Returns whether one was.
+    pub fn accept_key(&mut self, id: &str, public_key: &str) -> Result<bool> {
+        self.seen.insert(id.to_string(), public_key.to_string());
+        let unverified = self.verified.get(id).is_some_and(|key| key != public_key);
+        if unverified {
+            self.verified.remove(id);
+        }
+        self.save()?;
+        Ok(unverified)
+    }
+
+    pub fn is_verified(&self, id: &str) -> bool {
+        self.verified.contains_key(id)
+    }
+
+    /// Mark `id` as verified under `public_key` and persist the store.
+    pub fn verify(&mut self, id: &str, public_key: &str) -> Result<()> {
+        self.verified.insert(id.to_string(), public_key.to_string());
+        self.seen.insert(id.to_string(), public_key.to_string());
+        self.save()
+    }
+
+    pub fn is_declined(&self, id: &str) -> bool {
+        self.declined.contains(id)
+    }
+
+    /// Remember that `id`'s connection request was declined.
+    pub fn decline(&mut self, id: &str) -> Result<()> {
+        self.declined.insert(id.to_string());
+        self.save()
+    }
+
+    /// Forget a decline, once a connection with `id` is accepted after all.
+    pub fn undecline(&mut self, id: &str) -> Result<()> {
+        if self.declined.remove(id) {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn is_cancelled(&self, id: &str) -> bool {
+        self.cancelled.contains(id)
+    }
+
+    /// Remember that our request to `id` was withdrawn.
+    pub fn cancel(&mut self, id: &str) -> Result<()> {
+        self.cancelled.insert(id.to_string());
+        self.save()
+    }
+
+    /// Forget a cancellation, once a new connection to `id` is initiated.
+    pub fn uncancel(&mut self, id: &str) -> Result<()> {
+        if self.cancelled.remove(id) {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("No data directory available to store verified peers"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Cannot create {}", parent.display()))?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Cannot write {}", path.display()))
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|d| d.join("accord").join("tui").join("trust.json"))
+}
+
+/// Human-comparable fingerprint of a public key: the first 16 bytes of its
+/// SHA-256 digest as upper-case hex in groups of four.
+pub fn fingerprint(public_key: &str) -> String {
+    let digest = Sha256::digest(public_key.as_bytes());
+    digest[..16]
+        .chunks(2)
+        .map(|pair| format!("{:02X}{:02X}", pair[0], pair[1]))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
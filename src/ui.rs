@@ -1,51 +1,225 @@
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
+    },
     Frame,
 };
 
-use crate::app::App;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    app::{App, ContentGeometry, DisplayLine, ViewKind, FOLD_CLOSED, FOLD_OPEN, SEND_QUEUE_WARN},
+    commands,
+    events::KEYBINDINGS,
+};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-pub fn render(f: &mut Frame, app: &App) {
+/// Rows the prompt grows to for multi-line input before it scrolls.
+const PROMPT_MAX_LINES: usize = 6;
+
+/// Node status marker in the header, coloured by connectivity.
+pub const HEADER_DOT: &str = "●";
+
+/// Narrowest content area that is still split for the live events pane;
+/// below it the content keeps the full width.
+const SPLIT_MIN_WIDTH: u16 = 80;
+
+pub fn render(f: &mut Frame, app: &mut App) {
+    let quickbar = if app.show_quickbar && app.quickbar.iter().any(Option::is_some) { 1 } else { 0 };
+    let error_line = if app.last_error.is_some() { 1 } else { 0 };
+    let prompt_lines = app.prompt_input.split('\n').count().min(PROMPT_MAX_LINES) as u16;
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3), // header
-            Constraint::Min(0),    // content
-            Constraint::Length(3), // prompt
+            Constraint::Length(3),          // header
+            Constraint::Min(0),             // content
+            Constraint::Length(error_line), // error line
+            Constraint::Length(quickbar),   // quick-bar
+            Constraint::Length(2 + prompt_lines), // prompt
         ])
         .split(f.area());
 
     render_header(f, chunks[0], app);
-    render_content(f, chunks[1], app);
-    render_prompt(f, chunks[2], app);
+    if app.split_view && chunks[1].width >= SPLIT_MIN_WIDTH {
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(62), Constraint::Percentage(38)])
+            .split(chunks[1]);
+        render_content(f, panes[0], app);
+        render_live_events(f, panes[1], app);
+    } else {
+        render_content(f, chunks[1], app);
+    }
+    render_error_line(f, chunks[2], app);
+    render_quickbar(f, chunks[3], app);
+    render_prompt(f, chunks[4], app);
+    if app.palette.is_some() {
+        render_palette(f, f.area(), app);
+    }
+    if app.show_keys {
+        render_keys(f, f.area(), app);
+    }
+}
+
+/// The keybinding overlay: every key and what it does.
+fn render_keys(f: &mut Frame, area: Rect, app: &App) {
+    let popup = centered_rect(area, 66, KEYBINDINGS.len() as u16 + 2);
+    f.render_widget(Clear, popup);
+    let key_style = Style::default().fg(app.theme.header).add_modifier(Modifier::BOLD);
+    let lines: Vec<Line> = KEYBINDINGS
+        .iter()
+        .map(|(key, action)| Line::from(vec![Span::styled(format!(" {:<18}", key), key_style), Span::raw(*action)]))
+        .collect();
+    let block = Block::default()
+        .title(Span::styled(" Keys  (Esc or ? to close) ", Style::default().add_modifier(Modifier::BOLD)))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.help));
+    f.render_widget(Paragraph::new(lines).block(block), popup);
+}
+
+/// A `width` × `height` rectangle centred in `area`, clipped to it.
+fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// The command palette: a filter line over the matching commands, the
+/// selected one highlighted.
+fn render_palette(f: &mut Frame, area: Rect, app: &App) {
+    let Some(palette) = &app.palette else {
+        return;
+    };
+    let matches = commands::palette_matches(&palette.filter);
+    let popup = centered_rect(area, 90, 20);
+    f.render_widget(Clear, popup);
+
+    let block = Block::default()
+        .title(Span::styled(
+            " Commands  (type to filter, ↑↓, Enter, Esc) ",
+            Style::default().add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.help));
+    let inner = block.inner(popup);
+    f.render_widget(block, popup);
+    if inner.height < 2 {
+        return;
+    }
+
+    let filter = Paragraph::new(format!("> {}", palette.filter)).style(Style::default().fg(app.theme.text));
+    f.render_widget(filter, Rect { height: 1, ..inner });
+
+    // Keep the selection in view.
+    let rows = (inner.height - 1) as usize;
+    let first = palette.selected.saturating_sub(rows.saturating_sub(1));
+    let items: Vec<ListItem> = matches
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(rows)
+        .map(|(i, (usage, summary))| {
+            let item = ListItem::new(format!(" {:<45}{}", usage, summary));
+            if i == palette.selected {
+                item.style(Style::default().fg(app.theme.help).add_modifier(Modifier::REVERSED))
+            } else {
+                item
+            }
+        })
+        .collect();
+    let list_area = Rect { y: inner.y + 1, height: inner.height - 1, ..inner };
+    if items.is_empty() {
+        f.render_widget(Paragraph::new(" No matching command."), list_area);
+    } else {
+        f.render_widget(List::new(items), list_area);
+    }
 }
 
 fn render_header(f: &mut Frame, area: Rect, app: &App) {
+    let established = app.connections.iter().filter(|c| c.is_established()).count();
     let status = match &app.node_status {
-        crate::app::NodeStatus::Stopped => "●  Stopped".to_string(),
+        crate::app::NodeStatus::Stopped => "Stopped".to_string(),
+        crate::app::NodeStatus::Running { .. } => match &app.local_peer_id {
+            Some(peer_id) => format!(
+                "Running  (port {}, peer {})",
+                app.listen_port,
+                commands::short_peer_id(peer_id)
+            ),
+            None => format!("Running  (port {})", app.listen_port),
+        },
+    };
+    // The dot tells "up and talking to someone" from "up but isolated".
+    let dot_color = match &app.node_status {
+        crate::app::NodeStatus::Stopped => app.theme.error,
+        crate::app::NodeStatus::Running { .. } if established > 0 => app.theme.messages,
+        crate::app::NodeStatus::Running { .. } => app.theme.warn,
+    };
+
+    // Network at a glance: known peers, established and pending connections.
+    let network = match &app.node_status {
+        crate::app::NodeStatus::Stopped => String::new(),
         crate::app::NodeStatus::Running { .. } => {
-            format!("●  Running  (port {})", app.listen_port)
+            let pending = app.connections.len() - established;
+            format!("   │   peers {}  conn {} 🔒 {} 🔓", app.peers.len(), established, pending)
         }
     };
 
-    let title = Paragraph::new(format!(" Accord  v{}   │   {}", VERSION, status))
+    let depth = app.send_queue.depth();
+    let queue = match depth {
+        0 => String::new(),
+        n if n >= SEND_QUEUE_WARN => format!("   │   queue {} ⚠", n),
+        n => format!("   │   queue {}", n),
+    };
+    let lead = format!(" Accord  v{}   │   ", VERSION);
+    let rest = format!("  {}{}{}", status, network, queue);
+    let text = format!("{}{}{}", lead, HEADER_DOT, rest);
+    let block = Block::default().borders(Borders::ALL);
+    let inner = block.inner(area);
+    // A backed-up send queue turns the whole header to the warning colour.
+    let color = if depth >= SEND_QUEUE_WARN { app.theme.warn } else { app.theme.header };
+    let line = Line::from(vec![
+        Span::raw(lead),
+        Span::styled(HEADER_DOT, Style::default().fg(dot_color)),
+        Span::raw(rest),
+    ]);
+    let title = Paragraph::new(line)
         .style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(color)
                 .add_modifier(Modifier::BOLD),
         )
-        .block(Block::default().borders(Borders::ALL));
+        .block(block);
     f.render_widget(title, area);
+
+    // The sparkline only goes where it fits beside the status; on narrow
+    // terminals it is simply left out.
+    if app.show_sparkline {
+        let spark = format!("msgs {} {} ", app.activity.total(), app.activity.sparkline());
+        let spark_width = spark.width() as u16;
+        if inner.width >= text.width() as u16 + 3 + spark_width {
+            let spark_area = Rect {
+                x: inner.x + inner.width - spark_width,
+                width: spark_width,
+                ..inner
+            };
+            f.render_widget(Paragraph::new(spark).style(Style::default().fg(app.theme.messages)), spark_area);
+        }
+    }
 }
 
-fn render_content(f: &mut Frame, area: Rect, app: &App) {
-    let lines = &app.content_lines;
+fn render_content(f: &mut Frame, area: Rect, app: &mut App) {
+    let rows = app.display_lines();
     let visible_height = area.height.saturating_sub(2) as usize;
-    let total = lines.len();
+    let total = rows.len();
 
     let scroll_offset = if total <= visible_height {
         0
@@ -54,44 +228,193 @@ fn render_content(f: &mut Frame, area: Rect, app: &App) {
         (app.content_scroll as usize).min(max_scroll)
     };
 
-    let visible: Vec<ListItem> = lines
+    let visible: Vec<ListItem> = rows
         .iter()
         .skip(scroll_offset)
         .take(visible_height)
-        .map(|l| ListItem::new(l.as_str()))
+        .map(|row| match *row {
+            // Earlier outputs stay dim so the current one stands out.
+            DisplayLine::History(line) => {
+                ListItem::new(commands::printable(line)).style(Style::default().fg(app.theme.border))
+            }
+            DisplayLine::Text(i, line) => {
+                let mut style = app.theme.line_style(app.content_kind, line);
+                if let Some(search) = &app.search
+                    && let Some(n) = search.matches.iter().position(|&m| m == i)
+                {
+                    let mark = if n == search.current { Modifier::REVERSED } else { Modifier::UNDERLINED };
+                    style = style.add_modifier(mark);
+                }
+                // Times are turned into text here, at draw time, so relative
+                // ones stay current without re-running the command.
+                let time = app.content_times.get(i).copied().flatten().and_then(|at| app.time_display.label(at));
+                match time {
+                    Some(time) => ListItem::new(Line::from(vec![
+                        Span::styled(format!("{:>9}  ", time), Style::default().fg(app.theme.border)),
+                        Span::raw(commands::printable(line)),
+                    ]))
+                    .style(style),
+                    None => ListItem::new(commands::printable(line)).style(style),
+                }
+            }
+            DisplayLine::Fold(idx) => {
+                let fold = &app.content_folds[idx];
+                let marker = if fold.open { FOLD_OPEN } else { FOLD_CLOSED };
+                ListItem::new(format!("{} {}  ({} {})", marker, fold.label, fold.len, fold.unit))
+                    .style(Style::default().add_modifier(Modifier::BOLD))
+            }
+        })
         .collect();
 
     let title = if total > visible_height {
-        let pct = (scroll_offset * 100) / total.max(1);
+        // Measured against the furthest scroll, so the bottom reads 100%
+        // and agrees with the scrollbar thumb.
+        let pct = (scroll_offset * 100) / (total - visible_height);
         format!("{}({}%  PgUp/PgDn) ", app.content_title, pct)
     } else {
         app.content_title.clone()
     };
 
-    let block = Block::default()
-        .title(title)
+    let accent = app.theme.accent(app.content_kind, &app.content_lines);
+    let mut block = Block::default()
+        .title(Span::styled(title, Style::default().fg(accent).add_modifier(Modifier::BOLD)))
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::DarkGray));
+        .border_style(Style::default().fg(accent));
+    // The command behind the view, on the bottom border out of the way.
+    if let Some(cmd) = &app.content_command {
+        let cmd = Span::styled(format!(" {} ", cmd), Style::default().fg(app.theme.border));
+        block = block.title_bottom(Line::from(cmd).right_aligned());
+    }
+    // Kept views, numbered for Alt+1–9, on the top border.
+    if app.tab_order.len() > 1 {
+        let current = app.content_title.trim();
+        let tabs: Vec<Span> = app
+            .tab_order
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let style = if name == current {
+                    Style::default().fg(accent).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(app.theme.border)
+                };
+                Span::styled(format!(" {} {} ", i + 1, name), style)
+            })
+            .collect();
+        block = block.title(Line::from(tabs).right_aligned());
+    }
+    if let Some(search) = &app.search {
+        let find = format!(" find '{}'  {}/{} ", search.term, search.current + 1, search.matches.len());
+        block = block.title_bottom(Line::from(Span::styled(find, Style::default().fg(accent))).left_aligned());
+    }
 
     let list = List::new(visible).block(block);
     f.render_widget(list, area);
+
+    let scrollbar = area.inner(Margin { vertical: 1, horizontal: 0 });
+    let scrollbar = Rect { x: area.right().saturating_sub(1), width: 1, ..scrollbar };
+    app.content_geometry = ContentGeometry { area, scrollbar, visible: visible_height, total };
+
+    if total > visible_height {
+        let mut state = ScrollbarState::new(total - visible_height)
+            .position(scroll_offset)
+            .viewport_content_length(visible_height);
+        let bar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .style(Style::default().fg(accent));
+        f.render_stateful_widget(bar, scrollbar, &mut state);
+    }
+}
+
+/// The newest events, following the log as it grows, beside the content in
+/// the split layout.
+fn render_live_events(f: &mut Frame, area: Rect, app: &App) {
+    let visible_height = area.height.saturating_sub(2) as usize;
+    let skip = app.events.len().saturating_sub(visible_height);
+    let items: Vec<ListItem> = app
+        .events
+        .iter()
+        .skip(skip)
+        .map(|line| ListItem::new(commands::printable(line)).style(app.theme.line_style(ViewKind::Events, line)))
+        .collect();
+    let block = Block::default()
+        .title(Span::styled(" Live events ", Style::default().fg(app.theme.border).add_modifier(Modifier::BOLD)))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.border));
+    f.render_widget(List::new(items).block(block), area);
+}
+
+/// The last command error, kept under the content so a view redrawn over it
+/// doesn't hide why a command failed.
+fn render_error_line(f: &mut Frame, area: Rect, app: &App) {
+    let Some(error) = &app.last_error else {
+        return;
+    };
+    if area.height == 0 {
+        return;
+    }
+    let line = Line::from(vec![
+        Span::styled(format!(" ✗ {}", commands::printable(error)), Style::default().fg(app.theme.error).add_modifier(Modifier::BOLD)),
+        Span::styled("   (Ctrl+L dismiss)", Style::default().fg(app.theme.border)),
+    ]);
+    f.render_widget(Paragraph::new(line), area);
+}
+
+/// One row of `F<n> label` pairs for the bound function keys.
+fn render_quickbar(f: &mut Frame, area: Rect, app: &App) {
+    if area.height == 0 {
+        return;
+    }
+    let key_style = Style::default().fg(app.theme.header).add_modifier(Modifier::BOLD);
+    let label_style = Style::default().fg(app.theme.help);
+    let mut spans = Vec::new();
+    for (i, cmd) in app.quickbar.iter().enumerate() {
+        if let Some(cmd) = cmd {
+            spans.push(Span::styled(format!(" F{} ", i + 1), key_style));
+            spans.push(Span::styled(format!("{} ", commands::quickbar_label(cmd)), label_style));
+        }
+    }
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
 }
 
 fn render_prompt(f: &mut Frame, area: Rect, app: &App) {
-    let display = format!("> {}", app.prompt_input);
+    let prefix = format!("{} ", app.prompt_prefix);
+    // Lines after the first are indented to line up under the input.
+    let indent = " ".repeat(prefix.width());
+    let display: Vec<Line> = app
+        .prompt_input
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| Line::from(format!("{}{}", if i == 0 { &prefix } else { &indent }, line)))
+        .collect();
+
+    // Keep the cursor's line in view once the input outgrows the box.
+    let before = app.prompt_before_cursor();
+    let row = before.matches('\n').count();
+    let col = before.rsplit('\n').next().unwrap_or_default().width();
+    let rows = area.height.saturating_sub(2).max(1) as usize;
+    let scroll = row.saturating_sub(rows - 1);
+
     let prompt = Paragraph::new(display)
-        .style(Style::default().fg(Color::White))
+        .scroll((scroll as u16, 0))
+        .style(Style::default().fg(app.theme.text))
         .block(
             Block::default()
-                .title(" Prompt  (Enter=run  ↑↓=history  Esc=quit) ")
+                .title(if app.locked {
+                    " Prompt  (Enter=run  Alt+Enter=newline  ↑↓=history  ?=keys  locked: /quit to exit) "
+                } else {
+                    " Prompt  (Enter=run  Alt+Enter=newline  ↑↓=history  ?=keys  Esc=quit) "
+                })
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::DarkGray)),
+                .border_style(Style::default().fg(app.theme.border)),
         );
     f.render_widget(prompt, area);
 
-    // Position the cursor after the "> " prefix.
-    let cursor_x = area.x + 2 + app.prompt_input.len() as u16 + 1;
-    let cursor_y = area.y + 1;
+    // Position the cursor after the prefix and the input left of it on its
+    // line, measured in columns.
+    let cursor_x = area.x + 1 + (prefix.width() + col) as u16;
+    let cursor_y = area.y + 1 + (row - scroll) as u16;
     if cursor_x < area.x + area.width - 1 {
         f.set_cursor_position((cursor_x, cursor_y));
     }